@@ -1,21 +1,121 @@
 use num_parser::{
     self,
-    settings::{self, AngleUnit, Rounding},
+    settings::{self, DepthLimit, Rounding},
+    ErrorType, Expression, Value,
 };
 mod lib;
 use crate::app::lib::{
-    AppMode, ContextWrapper, CursorDir, HistoryEntry, Input, Queries, ScrollDir,
+    Answer, AppMode, ContextWrapper, CursorDir, DisplaySettings, Flash, HistoryEntry, Input,
+    PaneScroll, Queries, ScrollDir, Setting, VarsCache, MAX_DEPTH,
 };
+mod angle;
+mod complex;
+mod config;
+mod constants;
+mod dates;
+mod discrete;
+mod exact;
+mod finance;
+mod format;
 mod func;
+mod layout;
+mod matrix;
+mod mouse;
+mod percent;
+mod precise;
+mod programmer;
+mod series;
+mod simplify;
+mod stats;
+#[cfg(test)]
+mod testing;
+mod units;
+mod walker;
+mod worker;
 
-use crate::tui;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crate::{crash, tui};
+use angle::Angle;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use format::{Locale, NumberFormat};
+use layout::{LayoutSettings, Pane};
+use num::BigRational;
 use ratatui::{
     prelude::*,
     symbols::border,
     widgets::{block::*, *},
 };
 use std::io;
+use std::time::Duration;
+use worker::Progress;
+
+// Extra functions on top of num_parser's own, e.g. sum() and prod()
+// num_parser keeps built-ins in a global list, so this only needs calling once before anything is evaluated
+pub fn register_builtins() {
+    series::register();
+    programmer::register();
+    complex::register();
+    stats::register();
+    matrix::register();
+    finance::register();
+    discrete::register();
+    angle::register();
+}
+// Rewrites the bits of a query num_parser doesn't understand, e.g. 0xff or a decimal comma, before it gets evaluated
+fn preprocess(query: &str, display: &DisplaySettings) -> String {
+    let query = stats::preprocess(&format::delocalize(query, display.locale));
+    let query = discrete::preprocess(&percent::preprocess(&query));
+    let query = complex::preprocess(&angle::preprocess(&query));
+    programmer::preprocess(&query)
+}
+// Command line mode, works out a single query on a fresh context and gives back the line to print
+// Queries with dates or physical units in them go through dates.rs or units.rs, since num_parser doesn't know about either
+pub fn eval_once(query: &str) -> String {
+    let display = DisplaySettings::default();
+    let query = preprocess(query, &display);
+    let mut context = num_parser::Context::default();
+    constants::load(&mut context);
+    let special = dates::eval(&query, &context, &display)
+        .map(|result| result.map(|answer| answer.show(&context, &display)))
+        .or_else(|| units::eval(&query, &context, &display));
+    let result = match special {
+        Some(result) => result,
+        None => {
+            // like the worker, evaluated unrounded since format.rs does the rounding
+            let mut raw = context.clone();
+            raw.rounding = Rounding::NoRounding;
+            num_parser::eval_with_static_context(&query, &raw)
+                .map(|val| format::value(&val, &context, &display))
+        }
+        .map_err(describe_error),
+    };
+    match result {
+        Ok(res) => res,
+        Err(msg) => format!("Error - {}", msg),
+    }
+}
+// num_parser's own messages, except for running out of depth where its "INTERNAL ERROR" sounds like a bug
+fn describe_error(err: ErrorType) -> String {
+    match err {
+        ErrorType::RecursionDepthLimitReached { limit } if limit >= MAX_DEPTH => format!(
+            "Recursion went deeper than {}, which is as deep as the calculator can go",
+            limit
+        ),
+        ErrorType::RecursionDepthLimitReached { limit } => format!(
+            "Recursion went deeper than the limit of {}, it can be raised from Options <n>",
+            limit
+        ),
+        err => err.to_string(),
+    }
+}
+
+// how often the spinner and flash messages get looked at while there are any, see handle_events
+const TICK: Duration = Duration::from_millis(100);
+
+// where <x> saves the amortization table, in whatever directory the calculator was started from
+const AMORTIZATION_CSV: &str = "amortization.csv";
+
+// what the Options pane says, each line ends in the key for it so a click on the line can press it, see mouse.rs
+const OPTIONS_TEXT: &str = "\nAngle unit <d>\nComplex number display <i>\nChange rounding precision <r>\nNumber format <f>\nDigit grouping/decimal comma <l>\nSimplify a variable/function <s>\nRecursion depth limit <n>\nTime limit for working things out <w>\nAmortization table <t>\nProgrammer mode/word size <p>\nArbitrary precision <a>\nToggle exact fractions <e>\nToggle mixed numbers <m>\nToggle constants library <k>\nPane layout <v>\nClear user variables/functions <c>\n\nQuit: <q>\n";
 
 #[derive(Debug, Default)]
 pub struct App {
@@ -23,19 +123,57 @@ pub struct App {
     input: Input,
     // Current text in the output field, either last result or last error.
     last_output: String,
+    // The answer behind last_output, if the last query gave one. Kept so it can be shown in the current number format
+    last_answer: Option<Answer>,
+    // The same result as an exact fraction, only filled in when exact mode is on and the query stayed rational
+    last_exact: Option<BigRational>,
+    // And to however many decimal places precision mode asks for, if the query could be worked out that way
+    last_precise: Option<String>,
     // Vectors of user queries, if no errors occured and where no vars/functions were defined
     history: Queries,
     // Contains a num_parser context object. Stores the user variables/functions and is used for evaluating new queries. The wrapper is used to define the default initial state of the context, located in app/lib.rs
     ctxt: ContextWrapper<num_parser::Context>,
     // Enum describing app state - whether to display input/option etc. windows
     mode: AppMode,
+    // How results get displayed, on top of the rounding/angle settings in ctxt
+    display: DisplaySettings,
+    // The amortization table from the Options pane, and how far it's been scrolled
+    schedule: Vec<finance::Period>,
+    schedule_pos: usize,
+    // The thread queries get worked out on, and the query it's working out, if there is one
+    worker: worker::Worker,
+    job: Option<worker::Job>,
+    // A message that goes away by itself after a few seconds
+    flash: Option<Flash>,
+    // The Variables pane's lines, which take evaluating every variable, kept until the context or settings change
+    vars_cache: VarsCache,
+    // The terminal's size as of the last draw, so mouse clicks can be matched up with the panes
+    screen: Rect,
+    // How far the Variables, Functions, Constants and History panes have been scrolled with the wheel
+    scroll: PaneScroll,
+    // Pane sizes and which are shown, and the pane Options > Pane layout is changing
+    layout: LayoutSettings,
+    focus: Pane,
+    // Which list pane the compact layout for narrow terminals is showing
+    tab: Pane,
+    // Whether the screen already shows the current state, so it only gets redrawn after something happens
+    up_to_date: bool,
     // Triggered on exit
     exit: bool,
 }
 impl App {
     pub fn run(&mut self, terminal: &mut tui::Tui) -> io::Result<()> {
+        // the layout from last time, see config.rs
+        self.layout = LayoutSettings::load();
         while !self.exit {
-            terminal.draw(|frame| self.render_frame(frame))?;
+            if !self.up_to_date {
+                self.vars_cache.refresh(&self.ctxt, &self.display);
+                self.screen = terminal.size()?;
+                // what goes in the crash report if it comes to that, the history's kept up to date by archive()
+                crash::record_input(self.input.get_text());
+                terminal.draw(|frame| self.render_frame(frame))?;
+                self.up_to_date = true;
+            }
             self.handle_events()?;
         }
         Ok(())
@@ -49,15 +187,81 @@ impl App {
         frame.render_widget(self, frame.size());
     }
     fn handle_events(&mut self) -> io::Result<()> {
+        // With something on a timer (the spinner, a flash message) it ticks, waiting at most TICK for an event.
+        // Otherwise there's nothing to do until an event comes in, so it just waits
+        if self.job.is_some() || self.flash.is_some() {
+            self.tick();
+            if !event::poll(TICK)? {
+                return Ok(());
+            }
+        }
         match event::read()? {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event)
+                self.handle_key_event(key_event);
+                self.up_to_date = false;
             }
+            // the layout gets worked out from the terminal's size on every draw, so redrawing is all it takes
+            Event::Resize(_, _) => self.up_to_date = false,
+            Event::Mouse(mouse_event) => {
+                self.handle_mouse(mouse_event);
+                self.up_to_date = false;
+            }
+            Event::Paste(text) => {
+                self.handle_paste(&text);
+                self.up_to_date = false;
+            }
+            // the screen might have been messed with while the terminal was in the background
+            Event::FocusGained => self.up_to_date = false,
             _ => {}
         };
         Ok(())
     }
+    fn tick(&mut self) {
+        if self.job.is_some() {
+            self.check_job();
+            self.up_to_date = false;
+        }
+        if self.flash.as_ref().is_some_and(Flash::expired) {
+            self.flash = None;
+            self.up_to_date = false;
+        }
+    }
+    fn handle_paste(&mut self, text: &str) {
+        // the whole paste goes into the input at once, wherever there's an input to type into
+        if self.job.is_some() || matches!(self.mode, AppMode::Option | AppMode::AmortizationView) {
+            return;
+        }
+        // a query is one line, so line breaks and tabs (e.g. from copying out of a file) become spaces
+        let text = text.trim_end_matches(['\r', '\n']);
+        let line: String = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        if line != text {
+            self.flash = Some(Flash::new(
+                "Line breaks in the paste became spaces".to_string(),
+            ));
+        }
+        self.input.insert_str(&line);
+    }
     fn handle_key_event(&mut self, key_event: KeyEvent) {
+        // nothing else can happen until the query being worked out is done with
+        if self.job.is_some() {
+            let ctrl_c = key_event.code == KeyCode::Char('c')
+                && key_event.modifiers.contains(KeyModifiers::CONTROL);
+            if key_event.code == KeyCode::Esc || ctrl_c {
+                self.cancel("Cancelled".to_string());
+            }
+            return;
+        }
+        // F1-F4 pick the compact layout's tabs, see layout.rs
+        if let Some(tab) = match key_event.code {
+            KeyCode::F(n) => layout::tab_for_key(n),
+            _ => None,
+        } {
+            self.tab = tab;
+            return;
+        }
         match self.mode {
             AppMode::Normal => match key_event.code {
                 // All keybindings in the normal input mode.
@@ -98,43 +302,70 @@ impl App {
             AppMode::Option => match key_event.code {
                 // Option mode keybinds
                 KeyCode::Char('q') => self.exit(),
-                // maybe I should make a nicer way to return the context from App - TODO ?
-                KeyCode::Char('d') => match self.ctxt.angle_unit {
-                    // num_parser has a `turn` measure, 0->1, but I'll never use it
-                    // so we just toggle between degrees and radians
-                    settings::AngleUnit::Radian => self.ctxt.angle_unit = AngleUnit::Degree,
-                    _ => self.ctxt.angle_unit = AngleUnit::Radian,
-                },
-                KeyCode::Char('r') => {
-                    self.input.reset();
-                    self.mode = AppMode::RoundingSelect;
+                KeyCode::Char('d') => self.select(Setting::Angle),
+                KeyCode::Char('r') => self.select(Setting::Rounding),
+                KeyCode::Char('s') => self.select(Setting::Simplify),
+                KeyCode::Char('n') => self.select(Setting::Depth),
+                KeyCode::Char('w') => self.select(Setting::TimeLimit),
+                KeyCode::Char('t') => self.select(Setting::Amortization),
+                KeyCode::Char('p') => self.select(Setting::Programmer),
+                KeyCode::Char('a') => self.select(Setting::Precision),
+                KeyCode::Char('f') => self.select(Setting::Format),
+                KeyCode::Char('l') => self.select(Setting::Locale),
+                KeyCode::Char('v') => {
+                    let panes = self.panes(self.screen);
+                    self.focus = panes
+                        .shown()
+                        .first()
+                        .map_or(Pane::Output, |(pane, _)| *pane);
+                    self.mode = AppMode::LayoutSelect;
+                }
+                KeyCode::Char('i') => {
+                    self.display.complex = self.display.complex.next();
+                    self.vars_cache.invalidate();
+                }
+                KeyCode::Char('e') => {
+                    self.display.exact = !self.display.exact;
+                    self.vars_cache.invalidate();
+                }
+                KeyCode::Char('m') => {
+                    self.display.mixed = !self.display.mixed;
+                    self.vars_cache.invalidate();
+                }
+                KeyCode::Char('k') => {
+                    self.display.constants = !self.display.constants;
+                    if self.display.constants {
+                        constants::load(&mut self.ctxt);
+                    } else {
+                        constants::unload(&mut self.ctxt);
+                    }
+                    self.vars_cache.invalidate();
+                }
+                KeyCode::Char('c') => {
+                    self.ctxt = ContextWrapper::default();
+                    // the default context comes with the library loaded
+                    if !self.display.constants {
+                        constants::unload(&mut self.ctxt);
+                    }
+                    // and in radians
+                    self.display.angle = Angle::Radian;
+                    self.vars_cache.invalidate();
                 }
-                KeyCode::Char('c') => self.ctxt = ContextWrapper::default(),
                 KeyCode::Tab => self.mode = AppMode::Normal,
                 KeyCode::Esc => self.mode = AppMode::Normal,
                 _ => (),
             },
-            AppMode::RoundingSelect => match key_event.code {
-                KeyCode::Tab => {
-                    self.input.reset();
-                    self.mode = AppMode::Option
-                }
-                KeyCode::Esc => {
+            AppMode::Select(setting) => match key_event.code {
+                KeyCode::Tab | KeyCode::Esc => {
                     self.input.reset();
                     self.mode = AppMode::Option
                 }
                 KeyCode::Char(c) => self.input.insert(c),
                 KeyCode::Enter => {
-                    // if input is parseable as u8 then it becomes the new rounding accuracy
-                    // else we remove the rounding
-                    match self.input.get_text().parse::<u8>() {
-                        Ok(i) => {
-                            self.ctxt.rounding = settings::Rounding::Round(std::cmp::min(16u8, i))
-                        }
-                        Err(_) => self.ctxt.rounding = settings::Rounding::NoRounding,
-                    };
-                    self.input.reset();
                     self.mode = AppMode::Normal;
+                    self.apply(setting, self.input.get_text().trim());
+                    self.vars_cache.invalidate();
+                    self.input.reset();
                 }
                 KeyCode::Backspace => {
                     self.input.backspace();
@@ -143,31 +374,360 @@ impl App {
                 KeyCode::Right => self.input.shift(CursorDir::Right),
                 _ => (),
             },
+            AppMode::LayoutSelect => {
+                let before = self.layout;
+                match key_event.code {
+                    KeyCode::Tab | KeyCode::Esc => self.mode = AppMode::Option,
+                    KeyCode::Up | KeyCode::Down => self.move_focus(key_event.code == KeyCode::Down),
+                    KeyCode::Char('+') | KeyCode::Char('=') => self.layout.taller(self.focus, true),
+                    KeyCode::Char('-') => self.layout.taller(self.focus, false),
+                    KeyCode::Char('>') | KeyCode::Char('.') => self.layout.wider(self.focus, true),
+                    KeyCode::Char('<') | KeyCode::Char(',') => self.layout.wider(self.focus, false),
+                    KeyCode::Char('v') => self.layout.toggle(Pane::Vars),
+                    KeyCode::Char('f') => self.layout.toggle(Pane::Funcs),
+                    KeyCode::Char('h') => self.layout.toggle(Pane::History),
+                    KeyCode::Char('r') => self.layout = LayoutSettings::default(),
+                    _ => (),
+                }
+                if self.layout != before {
+                    // a hidden pane can't stay picked
+                    let panes = self.panes(self.screen);
+                    if !panes.shown().iter().any(|(pane, _)| *pane == self.focus) {
+                        self.focus = Pane::Output;
+                    }
+                    if let Err(err) = self.layout.save() {
+                        self.last_output = format!("Couldn't save the layout: {}", err);
+                        self.last_answer = None;
+                    }
+                }
+            }
+            AppMode::AmortizationView => match key_event.code {
+                KeyCode::Tab | KeyCode::Esc => self.mode = AppMode::Option,
+                KeyCode::Enter => self.mode = AppMode::Normal,
+                KeyCode::Up => self.schedule_pos = self.schedule_pos.saturating_sub(1),
+                KeyCode::Down => {
+                    self.schedule_pos =
+                        (self.schedule_pos + 1).min(self.schedule.len().saturating_sub(1))
+                }
+                KeyCode::PageUp => self.schedule_pos = self.schedule_pos.saturating_sub(12),
+                KeyCode::PageDown => {
+                    self.schedule_pos =
+                        (self.schedule_pos + 12).min(self.schedule.len().saturating_sub(1))
+                }
+                KeyCode::Char('x') => {
+                    // saving only needs a flash, but a failure stays in the Output pane
+                    match std::fs::write(AMORTIZATION_CSV, finance::to_csv(&self.schedule)) {
+                        Ok(()) => {
+                            let msg = format!("Saved the schedule to {}", AMORTIZATION_CSV);
+                            self.flash = Some(Flash::new(msg));
+                        }
+                        Err(err) => {
+                            self.last_output =
+                                format!("Couldn't save {}: {}", AMORTIZATION_CSV, err);
+                            self.last_answer = None;
+                        }
+                    }
+                    self.mode = AppMode::Normal;
+                }
+                _ => (),
+            },
+        }
+    }
+    fn output_text(&self) -> String {
+        // what goes in the Output pane for the last result
+        // in programmer mode integer results get shown in every base instead
+        // and in exact mode fractions get shown next to the decimal
+        // precision mode swaps the f64 decimal for the long one
+        // errors and messages in last_output get shown as they are
+        // and while a query's still being worked out there's a spinner instead
+        if let Some(job) = &self.job {
+            return job.status();
+        }
+        let bases = match &self.last_answer {
+            Some(Answer::Value(val)) if self.display.programmer => {
+                programmer::bases(val, &self.display)
+            }
+            _ => None,
+        };
+        let decimal = match (&self.last_precise, &self.last_answer) {
+            (Some(long), _) => format::localize(long, self.display.locale),
+            (None, Some(Answer::Value(val))) => {
+                let show = |x: &num_parser::Value| format::value(x, &self.ctxt, &self.display);
+                matrix::grid(val, show)
+                    .unwrap_or_else(|| format::value(val, &self.ctxt, &self.display))
+            }
+            (None, Some(answer)) => answer.show(&self.ctxt, &self.display),
+            _ => self.last_output.clone(),
+        };
+        match (bases, &self.last_exact) {
+            (Some(b), _) => b,
+            (None, Some(r)) if self.display.exact => exact::describe(r, &decimal, &self.display),
+            _ => decimal,
+        }
+    }
+    // where everything goes on a screen this size, see layout.rs
+    fn panes(&self, area: Rect) -> layout::Panes {
+        let picking = self.mode != AppMode::Normal;
+        layout::panes(
+            area,
+            &self.layout,
+            self.display.constants,
+            self.tab,
+            picking,
+        )
+    }
+    // <Up>/<Down> in Options > Pane layout go round the panes on screen
+    fn move_focus(&mut self, down: bool) {
+        let panes = self.panes(self.screen);
+        let shown: Vec<Pane> = panes.shown().into_iter().map(|(pane, _)| pane).collect();
+        let at = shown
+            .iter()
+            .position(|pane| *pane == self.focus)
+            .unwrap_or(0);
+        let next = match down {
+            true => (at + 1) % shown.len(),
+            false => (at + shown.len() - 1) % shown.len(),
+        };
+        self.focus = shown[next];
+    }
+    fn select(&mut self, setting: Setting) {
+        self.input.reset();
+        self.mode = AppMode::Select(setting);
+    }
+    // what was typed in for a setting when <Enter> was hit, afterwards it's back to Normal mode unless this says otherwise
+    fn apply(&mut self, setting: Setting, text: &str) {
+        match setting {
+            Setting::Rounding => {
+                // if input is parseable as u8 then it becomes the new rounding accuracy
+                // else we remove the rounding
+                self.ctxt.rounding = match text.parse::<u8>() {
+                    Ok(i) => settings::Rounding::Round(std::cmp::min(16u8, i)),
+                    Err(_) => settings::Rounding::NoRounding,
+                }
+            }
+            Setting::Programmer => {
+                // a word size like 32, or u32 for unsigned, turns programmer mode on
+                // anything else turns it off, same as the rounding select
+                let text = text.to_lowercase();
+                let (signed, size) = match text.strip_prefix('u') {
+                    Some(rest) => (false, rest),
+                    None => (true, text.strip_prefix('i').unwrap_or(&text)),
+                };
+                match size.parse::<u32>() {
+                    Ok(bits @ (8 | 16 | 32 | 64)) => {
+                        self.display.programmer = true;
+                        self.display.word_size = bits;
+                        self.display.signed = signed;
+                    }
+                    _ => self.display.programmer = false,
+                }
+            }
+            Setting::Precision => {
+                // a number of decimal places turns precision mode on, anything else turns it off
+                self.display.precision = match text.parse::<u32>() {
+                    Ok(n) => Some(n.min(precise::MAX_DIGITS)),
+                    Err(_) => None,
+                }
+            }
+            Setting::Angle => {
+                // anything unrecognised leaves the unit as it was
+                if let Some(angle) = Angle::parse(text) {
+                    angle.set(&mut self.ctxt);
+                    self.display.angle = angle;
+                }
+            }
+            Setting::Depth => {
+                // a number is the new limit, max is as deep as the stack allows, anything else leaves it as it was
+                let text = text.to_lowercase();
+                match text.parse::<u32>() {
+                    Ok(n) => self.ctxt.depth_limit = DepthLimit::Limit(n.clamp(1, MAX_DEPTH)),
+                    Err(_) if text == "max" => self.ctxt.depth_limit = DepthLimit::Limit(MAX_DEPTH),
+                    Err(_) => (),
+                }
+            }
+            Setting::TimeLimit => {
+                // a number of seconds is the new limit, none takes it away, anything else leaves it as it was
+                let text = text.to_lowercase();
+                match text.parse::<u64>() {
+                    Ok(n) => self.display.time_limit = Some(n.max(1)),
+                    Err(_) if text == "none" => self.display.time_limit = None,
+                    Err(_) => (),
+                }
+            }
+            Setting::Amortization => {
+                // the table replaces the Output pane until it's closed, errors go to the Output pane as usual
+                match self.amortize(text) {
+                    Ok(schedule) => {
+                        self.schedule = schedule;
+                        self.schedule_pos = 0;
+                        self.mode = AppMode::AmortizationView;
+                    }
+                    Err(msg) => self.last_output = msg,
+                }
+            }
+            Setting::Format => self.display.format = NumberFormat::parse(text),
+            Setting::Locale => self.display.locale = Locale::parse(text),
+            Setting::Simplify => self.simplify_definition(text),
+        }
+    }
+    fn amortize(&self, text: &str) -> Result<Vec<finance::Period>, String> {
+        // rate per period, number of payments and amount borrowed, typed in as a list so they can be worked out, e.g. 0.05/12, 30*12, 200000
+        let query = preprocess(&format!("[{}]", text), &self.display);
+        let query = angle::converted(&query, self.display.angle);
+        let mut raw = angle::context_in(&self.ctxt, self.display.angle);
+        raw.rounding = Rounding::NoRounding;
+        let values = num_parser::eval_with_static_context(&query, &raw).map_err(describe_error)?;
+        match values
+            .as_vector()
+            .iter()
+            .map(|v| v.as_float())
+            .collect::<Result<Vec<f64>, _>>()
+        {
+            Ok(args) if args.len() == 3 => finance::schedule(args[0], args[1], args[2]),
+            _ => Err(
+                "Enter the rate per period, the number of payments and the amount borrowed"
+                    .to_string(),
+            ),
+        }
+    }
+    fn simplify_definition(&mut self, name: &str) {
+        // replaces a stored variable or function body with its simplified form
+        // variables are checked first, same as num_parser does when it looks up a name
+        if let Some(body) = self.ctxt.get_var(name) {
+            let simple = simplify::simplify(&body);
+            self.last_output = format!("{} = {}", name, func::parser(&simple));
+            self.ctxt.add_variable(name.to_string(), Box::new(simple));
+        } else if let Some((params, body)) = self.ctxt.get_function(name) {
+            let simple = simplify::simplify(&body);
+            self.last_output =
+                format!("{}({}) = {}", name, params.join(","), func::parser(&simple));
+            self.ctxt
+                .add_function(name.to_string(), params, Box::new(simple));
+        } else {
+            self.last_output = format!("No variable or function called '{}'", name);
         }
     }
     fn evaluate(&mut self) {
         // workhorse
         // does actual evaluation of user inputs
         // eval_with_mutable_context allows user defined variables and functions
-        let out = num_parser::eval_with_mutable_context(&*self.input.get_text(), &mut self.ctxt);
+        let query = preprocess(&self.input.get_text(), &self.display);
         self.history.scroll_reset();
-        match out {
+        self.last_answer = None;
+        self.last_exact = None;
+        self.last_precise = None;
+        if let Some(msg) = constants::check_declaration(&query).filter(|_| self.display.constants) {
+            self.last_output = msg;
+            return;
+        }
+        // dates and units both give back text rather than a num_parser value, and neither does declarations
+        let (converted, working) = (
+            angle::converted(&query, self.display.angle),
+            angle::context_in(&self.ctxt, self.display.angle),
+        );
+        let special = dates::eval(&converted, &working, &self.display).or_else(|| {
+            units::eval(&converted, &working, &self.display).map(|result| result.map(Answer::Text))
+        });
+        if let Some(result) = special {
+            match result {
+                Ok(answer) => {
+                    self.last_output = answer.show(&self.ctxt, &self.display);
+                    self.archive(self.input.get_text(), answer.clone());
+                    self.last_answer = Some(answer);
+                    self.input.reset();
+                }
+                Err(msg) => self.last_output = msg,
+            }
+            return;
+        }
+        // a placeholder lets num_parser see a new multi-letter name as one name, see func::new_name
+        let placeholder = func::new_name(&query, &self.ctxt);
+        if let Some(name) = &placeholder {
+            self.ctxt
+                .add_variable(name.clone(), Box::new(Expression::Literal(Value::Int(0))));
+        }
+        // the rest happens on another thread so the screen keeps going, see worker.rs and finish()
+        self.job = Some(self.worker.start(
+            query,
+            self.ctxt.clone(),
+            self.display.exact,
+            self.display.precision,
+            self.display.angle,
+            self.input.get_text(),
+            placeholder,
+        ));
+    }
+    fn check_job(&mut self) {
+        // called while a query's being worked out, picks up its answer or gives up on it
+        let Some(job) = &self.job else {
+            return;
+        };
+        match job.progress() {
+            Progress::Working => {
+                let limit = self.display.time_limit.map(Duration::from_secs);
+                if limit.is_some_and(|limit| job.elapsed() > limit) {
+                    let msg = format!(
+                        "Gave up after {} seconds, the time limit can be changed from Options <w>",
+                        job.elapsed().as_secs()
+                    );
+                    self.cancel(msg);
+                }
+            }
+            Progress::Finished(done) => {
+                if let Some(job) = self.job.take() {
+                    self.finish(job, *done);
+                }
+            }
+            Progress::Lost => self.cancel("Something went wrong working that out".to_string()),
+        }
+    }
+    fn cancel(&mut self, msg: String) {
+        // the context never got touched, except for a placeholder name which has to go again
+        if let Some(job) = self.job.take() {
+            self.worker.cancel(&job);
+            if let Some(name) = job.placeholder {
+                self.ctxt.variables.remove(&name);
+            }
+        }
+        self.last_output = msg;
+    }
+    fn archive(&mut self, input: String, answer: Answer) {
+        self.history.archive(input, answer);
+        crash::record_history(self.history.render_all(&self.ctxt, &self.display));
+    }
+    fn finish(&mut self, job: worker::Job, done: worker::Done) {
+        // the worker's copy of the context has any new declaration in it
+        *self.ctxt = done.context;
+        self.vars_cache.invalidate();
+        let query = job.query;
+        if let (Some(name), Err(_)) = (&job.placeholder, &done.out) {
+            self.ctxt.variables.remove(name);
+        }
+        match done.out {
             Ok(res) => match res {
                 Some(val) => {
                     // if user query is evaluated without error:
-                    self.history.archive(self.input.get_text(), val.to_string());
-                    self.last_output = self.history.retrieve(HistoryEntry::Value(0)).clone(); // display it in top pane
+                    self.archive(job.input, Answer::Value(val.clone()));
+                    self.last_output = self.history.retrieve(HistoryEntry::Value(0)); // display it in top pane
+                    self.last_answer = Some(Answer::Value(val));
+                    // exact and precision mode both worked the query out again from its parsed tree
+                    self.last_exact = done.exact;
+                    self.last_precise = done.precise;
                     self.input.reset(); // clear the current input
                 }
                 None => {
                     // no error and no return value occurs when user inputs a variable/function definition
                     // so just clear the in/output
                     // so just clear the in/output
+                    if let Some(i) = func::assignment(&query) {
+                        let name: String = query.chars().take(i).collect();
+                        discrete::freeze(name.trim(), &mut self.ctxt, self.display.angle);
+                    }
                     self.input.reset();
                     self.last_output = "".to_string();
                 }
             },
-            Err(err) => self.last_output = err.to_string(),
+            Err(err) => self.last_output = describe_error(err),
         }
     }
 }
@@ -183,6 +743,18 @@ impl Widget for &App {
             Paragraph::new(contents)
                 .centered()
                 .alignment(Alignment::Center)
+                // wrapped so long results (e.g. from precision mode) aren't just cut off
+                .wrap(Wrap { trim: false })
+                .block(blck)
+                .render(location, buf);
+        }
+        // the same for the list panes, which the mouse wheel can scroll, never past the last row
+        fn make_list(contents: &str, blck: Block, scroll: u16, location: Rect, buf: &mut Buffer) {
+            let scroll = scroll.min(layout::max_scroll(contents, location));
+            Paragraph::new(Text::from(contents))
+                .centered()
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0))
                 .block(blck)
                 .render(location, buf);
         }
@@ -190,10 +762,14 @@ impl Widget for &App {
             inp: String,
             lens: (usize, usize),
             scroller_pos: usize,
+            flash: Option<&Flash>,
             loc: Rect,
             buf: &mut Buffer,
         ) {
-            let input_title = Title::from(" Input ".bold());
+            let input_title = match flash {
+                Some(flash) => Title::from(format!(" {} ", flash.text).bold().reversed()),
+                None => Title::from(" Input ".bold()),
+            };
             let mut instructions_raw = vec![
                 " Options:".into(),
                 "<Tab> ".bold(),
@@ -244,40 +820,186 @@ impl Widget for &App {
                 )
                 .borders(Borders::ALL)
                 .border_set(border::THICK);
-            let options_content = Text::from(
-                "\nToggle angle measure: <d>\nChange rounding precision <r>\nClear user variables/functions <c>\n\nQuit: <q>\n",
+            make_para(Text::from(OPTIONS_TEXT), options_block, loc, buf)
+        }
+        fn render_select(
+            setting: Setting,
+            prompt: String,
+            inp: String,
+            loc: Rect,
+            buf: &mut Buffer,
+        ) {
+            let select_title = Title::from(format!(" Options: {} ", setting.title()).bold());
+            let select_instructions = Title::from(Line::from(vec![
+                " Back to Options: ".into(),
+                "<Tab> ".bold(),
+            ]));
+            let select_block = Block::default()
+                .title(select_title.alignment(Alignment::Center))
+                .title(
+                    select_instructions
+                        .alignment(Alignment::Center)
+                        .position(Position::Bottom),
+                )
+                .borders(Borders::ALL)
+                .border_set(border::THICK);
+            let select_content = Text::from(format!("\n{}\n\n:> {}", prompt, &*inp));
+            make_para(select_content, select_block, loc, buf)
+        }
+        fn render_schedule(
+            schedule: &[finance::Period],
+            pos: usize,
+            locale: Locale,
+            loc: Rect,
+            buf: &mut Buffer,
+        ) {
+            let schedule_title = Title::from(" Amortization ".bold());
+            let schedule_instructions = Title::from(Line::from(vec![
+                " Scroll: ".into(),
+                "<Up>/<Down>/<PgUp>/<PgDn>".bold(),
+                ", Save as CSV: ".into(),
+                "<x>".bold(),
+                ", Back: ".into(),
+                "<Tab> ".bold(),
+            ]));
+            let schedule_block = Block::default()
+                .title(schedule_title.alignment(Alignment::Center))
+                .title(
+                    schedule_instructions
+                        .alignment(Alignment::Center)
+                        .position(Position::Bottom),
+                )
+                .borders(Borders::ALL)
+                .border_set(border::THICK);
+            let money = |x: f64| {
+                Cell::from(
+                    Text::from(format::localize(&format!("{:.2}", x), locale))
+                        .alignment(Alignment::Right),
+                )
+            };
+            let rows = schedule.iter().skip(pos).map(|p| {
+                Row::new(vec![
+                    Cell::from(Text::from(p.number.to_string()).alignment(Alignment::Right)),
+                    money(p.payment),
+                    money(p.interest),
+                    money(p.principal),
+                    money(p.balance),
+                ])
+            });
+            let header = Row::new(
+                ["#", "Payment", "Interest", "Principal", "Balance"]
+                    .map(|h| Cell::from(Text::from(h).alignment(Alignment::Right))),
+            )
+            .bold();
+            let widths = [
+                Constraint::Length(5),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+            ];
+            Clear.render(loc, buf);
+            Widget::render(
+                Table::new(rows, widths)
+                    .header(header)
+                    .block(schedule_block),
+                loc,
+                buf,
             );
-            make_para(options_content, options_block, loc, buf)
         }
-        fn render_rounding(inp: String, loc: Rect, buf: &mut Buffer) {
-            let rounding_title = Title::from(" Options: Rounding ".bold());
-            let rounding_instructions = Title::from(Line::from(vec![
+        fn render_layout(focus: Pane, loc: Rect, buf: &mut Buffer) {
+            let layout_title = Title::from(" Options: Pane Layout ".bold());
+            let layout_instructions = Title::from(Line::from(vec![
                 " Back to Options: ".into(),
                 "<Tab> ".bold(),
             ]));
-            let rounding_block = Block::default()
-                .title(rounding_title.alignment(Alignment::Center))
+            let layout_block = Block::default()
+                .title(layout_title.alignment(Alignment::Center))
                 .title(
-                    rounding_instructions
+                    layout_instructions
                         .alignment(Alignment::Center)
                         .position(Position::Bottom),
                 )
                 .borders(Borders::ALL)
                 .border_set(border::THICK);
-            let rounding_content =
-                Text::from(format!("{}\n:> {}", "\nEnter an integer between 0 and 16 and hit <Enter> \nAny non-integer input will lead to no rounding\n", &*inp));
-            make_para(rounding_content, rounding_block, loc, buf)
+            let layout_content = Text::from(format!(
+                "\nChanging: {}, pick another with <Up>/<Down>\nTaller/shorter <+>/<->  Wider/narrower <>>/<<>\nShow/hide User Variables <v>, User Functions <f>, History <h>\nBack to the default layout <r>\nChanges are saved for next time",
+                focus.name()
+            ));
+            make_para(layout_content, layout_block, loc, buf)
+        }
+        // picks out the pane being changed in Options > Pane layout by colouring its border, the title's left alone
+        fn highlight(loc: Rect, buf: &mut Buffer) {
+            let style = Style::new().yellow();
+            let edges = [
+                Rect { height: 1, ..loc },
+                Rect {
+                    y: loc.bottom().saturating_sub(1),
+                    height: 1,
+                    ..loc
+                },
+                Rect { width: 1, ..loc },
+                Rect {
+                    x: loc.right().saturating_sub(1),
+                    width: 1,
+                    ..loc
+                },
+            ];
+            for edge in edges {
+                buf.set_style(edge, style);
+            }
         }
-        fn render_vars(context: &num_parser::Context, loc: Rect, buf: &mut Buffer) {
-            let var_strings: Vec<String> = func::vars_to_strings(&context);
+        // the compact layout's tab bar, the tab that's showing is picked out
+        fn render_tabs(tabs: &[Pane], current: Option<Pane>, loc: Rect, buf: &mut Buffer) {
+            let labels: Vec<Span> = tabs
+                .iter()
+                .map(|tab| match Some(*tab) == current {
+                    true => layout::tab_label(*tab).bold().reversed(),
+                    false => layout::tab_label(*tab).into(),
+                })
+                .collect();
+            Paragraph::new(Line::from(labels)).render(loc, buf);
+        }
+        // For terminals too short for the panes, the input then whatever would be in the Output pane, on one line.
+        // The cursor's the character under it shown reversed, as there's no line below for the ^
+        fn render_minimal(input: &Input, shown: &str, loc: Rect, buf: &mut Buffer) {
+            let text = input.get_text();
+            let (before, after) = text.split_at(input.get_lens().0);
+            let mut after = after.chars();
+            let under = after.next().map_or(" ".to_string(), String::from);
+            let shown = shown
+                .lines()
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let line = Line::from(vec![
+                ":> ".bold(),
+                before.into(),
+                under.reversed(),
+                after.as_str().to_string().into(),
+                "  │ ".into(),
+                shown.into(),
+            ]);
+            Paragraph::new(line).render(loc, buf);
+        }
+        fn render_vars(var_strings: &[String], scroll: u16, loc: Rect, buf: &mut Buffer) {
             let vars_title = Title::from(" User Variables ".bold());
             let vars_block = Block::default()
                 .title(vars_title.alignment(Alignment::Center))
                 .borders(Borders::ALL)
                 .border_set(border::THICK);
-            make_para(Text::from(&*var_strings.concat()), vars_block, loc, buf);
+            make_list(&var_strings.concat(), vars_block, scroll, loc, buf);
+        }
+        fn render_constants(scroll: u16, loc: Rect, buf: &mut Buffer) {
+            let constants_title = Title::from(" Constants ".bold());
+            let constants_block = Block::default()
+                .title(constants_title.alignment(Alignment::Center))
+                .borders(Borders::ALL)
+                .border_set(border::THICK);
+            let constant_strings = constants::to_strings().concat();
+            make_list(&constant_strings, constants_block, scroll, loc, buf);
         }
-        fn render_funcs(context: &num_parser::Context, loc: Rect, buf: &mut Buffer) {
+        fn render_funcs(context: &num_parser::Context, scroll: u16, loc: Rect, buf: &mut Buffer) {
             let func_strings: Vec<String> = func::funcs_to_strings(context);
 
             let funcs_title = Title::from(" User Functions ".bold());
@@ -285,29 +1007,49 @@ impl Widget for &App {
                 .title(funcs_title.alignment(Alignment::Center))
                 .borders(Borders::ALL)
                 .border_set(border::THICK);
-            make_para(Text::from(&*func_strings.concat()), funcs_block, loc, buf);
+            make_list(&func_strings.concat(), funcs_block, scroll, loc, buf);
         }
         fn render_output(
-            last_out: &String,
+            shown: String,
             debug_text: String,
             context: &num_parser::Context,
+            display: &DisplaySettings,
             loc: Rect,
             buf: &mut Buffer,
         ) {
             let result_title = Title::from(" Output ".bold());
             let round_fmt = match context.rounding {
-                Rounding::Round(n) => format!("{} d.p.", n.to_string()),
+                Rounding::Round(n) => format!("{} d.p.", n),
                 Rounding::NoRounding => "None".to_string(),
             };
-            let ang_fmt = match context.angle_unit {
-                AngleUnit::Radian => "Rad ".to_string(),
-                AngleUnit::Degree => "Deg ".to_string(),
-                AngleUnit::Turn => "Turn ".to_string(),
+            let depth_fmt = match context.depth_limit {
+                DepthLimit::Limit(n) if n >= MAX_DEPTH => format!("{} (max)", n),
+                DepthLimit::Limit(n) => n.to_string(),
+                DepthLimit::NoLimit => "None".to_string(),
             };
-            let ctxt_settings = Title::from(Line::from(vec![
+            let mut settings_raw = vec![
                 format!(" Rounding: {}, ", round_fmt).into(),
-                format!("Angle units: {}", ang_fmt).into(),
-            ]));
+                format!("Angle units: {}, ", display.angle.name()).into(),
+                format!("Complex: {}, ", display.complex.name()).into(),
+                format!("Depth: {} ", depth_fmt).into(),
+            ];
+            if display.programmer {
+                settings_raw.push(format!(", Word: {} ", display.word_name()).into());
+            }
+            if display.exact {
+                let fractions = if display.mixed { "mixed" } else { "exact" };
+                settings_raw.push(format!(", Fractions: {} ", fractions).into());
+            }
+            if display.format != NumberFormat::Fixed {
+                settings_raw.push(format!(", Format: {} ", display.format.name()).into());
+            }
+            if display.locale != Locale::default() {
+                settings_raw.push(format!(", Numbers: {} ", display.locale.name()).into());
+            }
+            if let Some(digits) = display.precision {
+                settings_raw.push(format!(", Precision: {} d.p. ", digits).into());
+            }
+            let ctxt_settings = Title::from(Line::from(settings_raw));
 
             let result_block = Block::default()
                 .title(result_title.alignment(Alignment::Center))
@@ -319,60 +1061,234 @@ impl Widget for &App {
                 .borders(Borders::ALL)
                 .border_set(border::THICK);
             make_para(
-                Text::from(format!("\n{}\n{}", last_out, debug_text)),
+                Text::from(format!("\n{}\n{}", shown, debug_text)),
                 result_block,
                 loc,
                 buf,
             );
         }
-        fn render_history(hist_strings: Vec<String>, loc: Rect, buf: &mut Buffer) {
+        fn render_history(hist_strings: Vec<String>, scroll: u16, loc: Rect, buf: &mut Buffer) {
             let hist_title = Title::from(" History ".bold());
             let hist_block = Block::default()
                 .title(hist_title.alignment(Alignment::Center))
                 .borders(Borders::ALL)
                 .border_set(border::THICK);
-            make_para(Text::from(&*hist_strings.concat()), hist_block, loc, buf);
-        }
-        // LAYOUT
-        let thirds = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                Constraint::Percentage(25),
-                Constraint::Percentage(50),
-                Constraint::Percentage(25),
-            ])
-            .split(area);
-
-        let left = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(thirds[0]);
-
-        let middle = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(vec![Constraint::Percentage(60), Constraint::Percentage(40)])
-            .split(thirds[1]);
+            make_list(&hist_strings.concat(), hist_block, scroll, loc, buf);
+        }
+        // LAYOUT, see layout.rs
+        if layout::minimal(area) && self.mode == AppMode::Normal {
+            let shown = match &self.flash {
+                Some(flash) => flash.text.clone(),
+                None => self.output_text(),
+            };
+            render_minimal(&self.input, &shown, area, buf);
+            return;
+        }
+        let panes = self.panes(area);
+        if let Some(loc) = panes.tabs {
+            let tabs = layout::tabs(&self.layout, self.display.constants);
+            let current = layout::current_tab(&self.layout, self.display.constants, self.tab);
+            render_tabs(&tabs, current, loc, buf);
+        }
 
-        render_vars(&self.ctxt, left[0], buf);
-        render_funcs(&self.ctxt, left[1], buf);
-        render_output(
-            &self.last_output,
-            self.history.get_pos().to_string(),
-            &self.ctxt,
-            middle[0],
-            buf,
-        );
-        render_history(self.history.render_all(), thirds[2], buf);
+        if let Some(loc) = panes.vars {
+            render_vars(self.vars_cache.lines(), self.scroll.vars, loc, buf);
+        }
+        if let Some(loc) = panes.funcs {
+            render_funcs(&self.ctxt, self.scroll.funcs, loc, buf);
+        }
+        if let Some(loc) = panes.constants {
+            render_constants(self.scroll.constants, loc, buf);
+        }
+        // the output has no room when the screen's too short for anything but the one line
+        if !panes.output.is_empty() {
+            render_output(
+                self.output_text(),
+                self.history.get_pos().to_string(),
+                &self.ctxt,
+                &self.display,
+                panes.output,
+                buf,
+            );
+        }
+        if let Some(loc) = panes.history {
+            render_history(
+                self.history.render_all(&self.ctxt, &self.display),
+                self.scroll.history,
+                loc,
+                buf,
+            );
+        }
         match self.mode {
-            AppMode::Option => render_options(middle[1], buf),
+            AppMode::Option => render_options(panes.input, buf),
             AppMode::Normal => render_normal(
                 self.input.get_text(),
                 self.input.get_lens(),
                 self.history.get_pos(),
-                middle[1],
+                self.flash.as_ref(),
+                panes.input,
+                buf,
+            ),
+            AppMode::Select(setting) => render_select(
+                setting,
+                setting.prompt(&self.display),
+                self.input.get_text(),
+                panes.input,
                 buf,
             ),
-            AppMode::RoundingSelect => render_rounding(self.input.get_text(), middle[1], buf),
+            AppMode::LayoutSelect => render_layout(self.focus, panes.input, buf),
+            // the table covers the Output pane too, it needs the room
+            AppMode::AmortizationView => render_schedule(
+                &self.schedule,
+                self.schedule_pos,
+                self.display.locale,
+                panes.middle,
+                buf,
+            ),
+        }
+        if self.mode == AppMode::LayoutSelect {
+            let focused = panes
+                .shown()
+                .into_iter()
+                .find(|(pane, _)| *pane == self.focus);
+            if let Some((_, loc)) = focused {
+                highlight(loc, buf);
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::{press, type_in};
+
+    // ten billion terms, which would take all day
+    const SLOW: &str = "sum(sum(j, j, 1, 100000), k, 1, 100000)";
+
+    fn start_slow(app: &mut App) {
+        SLOW.chars().for_each(|c| press(app, KeyCode::Char(c)));
+        press(app, KeyCode::Enter);
+        assert!(app.job.is_some());
+        assert!(app.output_text().contains("Working it out"));
+    }
+    fn pick_depth(app: &mut App, text: &str) {
+        press(app, KeyCode::Tab);
+        press(app, KeyCode::Char('n'));
+        assert_eq!(app.mode, AppMode::Select(Setting::Depth));
+        type_in(app, text);
+        assert_eq!(app.mode, AppMode::Normal);
+    }
+
+    #[test]
+    fn rounding_error_comes_out_as_0() {
+        let mut app = testing::app();
+        app.ctxt.rounding = Rounding::Round(8);
+        type_in(&mut app, "sin(pi)");
+        assert_eq!(app.output_text(), "0");
+    }
+
+    #[test]
+    fn matrix_products_need_matmul() {
+        let mut app = testing::app();
+        type_in(&mut app, "[[1,2],[3,4]] * [[1,2],[3,4]]");
+        assert!(app.last_output.contains("matmul(a, b)"));
+        press(&mut app, KeyCode::Esc);
+        type_in(&mut app, "matmul([[1,2],[3,4]], [[1,2],[3,4]])");
+        assert_eq!(app.last_output, "[[7, 10], [15, 22]]");
+    }
+
+    #[test]
+    fn scrolling_an_empty_schedule() {
+        let mut app = testing::app();
+        app.mode = AppMode::AmortizationView;
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::PageDown);
+        assert_eq!(app.schedule_pos, 0);
+    }
+
+    #[test]
+    fn depth_picker() {
+        let mut app = testing::app();
+        let prompt = Setting::Depth.prompt(&app.display);
+        assert!(prompt.contains("max for 2000"));
+        pick_depth(&mut app, "50");
+        assert!(matches!(app.ctxt.depth_limit, DepthLimit::Limit(50)));
+        // out of range is clamped, and anything that isn't a number leaves it alone
+        pick_depth(&mut app, "99999");
+        assert!(matches!(app.ctxt.depth_limit, DepthLimit::Limit(MAX_DEPTH)));
+        pick_depth(&mut app, "0");
+        assert!(matches!(app.ctxt.depth_limit, DepthLimit::Limit(1)));
+        pick_depth(&mut app, "deep");
+        assert!(matches!(app.ctxt.depth_limit, DepthLimit::Limit(1)));
+        // max is as deep as the stack can go, with an error that doesn't send you back to Options
+        pick_depth(&mut app, " Max ");
+        assert!(matches!(app.ctxt.depth_limit, DepthLimit::Limit(MAX_DEPTH)));
+        // none isn't a way out of the limit any more, so it's left alone like anything else
+        pick_depth(&mut app, "1");
+        pick_depth(&mut app, "none");
+        assert!(matches!(app.ctxt.depth_limit, DepthLimit::Limit(1)));
+        pick_depth(&mut app, "max");
+        assert!(matches!(app.ctxt.depth_limit, DepthLimit::Limit(MAX_DEPTH)));
+        let err = describe_error(ErrorType::RecursionDepthLimitReached { limit: MAX_DEPTH });
+        assert!(err.contains("as deep as the calculator can go"));
+    }
+
+    #[test]
+    fn going_too_deep_says_how_to_fix_it() {
+        let mut app = testing::app();
+        pick_depth(&mut app, "100");
+        type_in(&mut app, "f(n) = branch(n < 1, 0, 1 + f(n - 1))");
+        type_in(&mut app, "f(5)");
+        assert_eq!(app.last_output, "5");
+        type_in(&mut app, "f(50)");
+        assert!(app.last_output.contains("limit of 100"));
+        assert!(app.last_output.contains("Options <n>"));
+    }
+
+    #[test]
+    fn slow_queries_can_be_cancelled() {
+        let mut app = testing::app();
+        start_slow(&mut app);
+        // other keys are ignored while it works
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Esc);
+        assert!(app.job.is_none());
+        assert_eq!(app.last_output, "Cancelled");
+        assert_eq!(app.input.get_text(), SLOW);
+        // and once it's gone everything works as normal again
+        press(&mut app, KeyCode::Esc);
+        type_in(&mut app, "1 + 1");
+        assert_eq!(app.last_output, "2");
+    }
+
+    #[test]
+    fn slow_queries_run_out_of_time() {
+        let mut app = testing::app();
+        start_slow(&mut app);
+        app.display.time_limit = Some(0);
+        std::thread::sleep(Duration::from_millis(5));
+        app.check_job();
+        assert!(app.job.is_none());
+        assert!(app.last_output.starts_with("Gave up after 0 seconds"));
+    }
+
+    #[test]
+    fn pastes_go_in_as_one_line() {
+        let mut app = testing::app();
+        app.handle_paste("1 + 2");
+        assert_eq!(app.input.get_text(), "1 + 2");
+        assert!(app.flash.is_none());
+        // a trailing newline from copying a whole line is just dropped
+        app.handle_paste(" * 3\n");
+        assert_eq!(app.input.get_text(), "1 + 2 * 3");
+        assert!(app.flash.is_none());
+        app.handle_paste("\n+\t4");
+        assert_eq!(app.input.get_text(), "1 + 2 * 3 + 4");
+        assert!(app.flash.is_some());
+        // nowhere to type in the Options pane
+        press(&mut app, KeyCode::Tab);
+        app.handle_paste("5");
+        assert_eq!(app.input.get_text(), "1 + 2 * 3 + 4");
+    }
+}
@@ -0,0 +1,381 @@
+// angle units - radians, degrees, gradians and turns, plus suffixes like 30° that say what unit a single value is in
+// num_parser's AngleUnit only has radians, degrees and turns, and its built-in functions can't be swapped out
+// (remove_built_in_function deadlocks), so gradians are done by converting the trig calls of whatever's being worked out,
+// see in_gradians() and context_in(). What gets stored is never converted, so f(x) = sin(x) declared in degrees
+// is still sin(x), and a = 30° is deg(30), both of which come out right in whatever unit they're used in later
+use crate::app::{func, stats};
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    settings::AngleUnit,
+    Context, EvalResult, Expression, Value,
+};
+use std::f64::consts::TAU;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Angle {
+    #[default]
+    Radian,
+    Degree,
+    Gradian,
+    Turn,
+}
+impl Angle {
+    // Reads what was typed into the angle select
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "rad" | "r" | "radian" | "radians" => Some(Angle::Radian),
+            "deg" | "d" | "degree" | "degrees" => Some(Angle::Degree),
+            "grad" | "g" | "gradian" | "gradians" | "gon" => Some(Angle::Gradian),
+            "turn" | "t" | "turns" => Some(Angle::Turn),
+            _ => None,
+        }
+    }
+    // What the Output pane title shows
+    pub fn name(&self) -> &str {
+        match self {
+            Angle::Radian => "Rad",
+            Angle::Degree => "Deg",
+            Angle::Gradian => "Grad",
+            Angle::Turn => "Turn",
+        }
+    }
+    // Written after an angle in polar form, so that what's shown can be typed back in
+    pub fn suffix(&self) -> &str {
+        match self {
+            Angle::Radian => "",
+            Angle::Degree => "°",
+            Angle::Gradian => " grad",
+            Angle::Turn => " turn",
+        }
+    }
+    // None for radians, which the exact engine can't convert to or from
+    pub fn per_turn(&self) -> Option<i64> {
+        match self {
+            Angle::Radian => None,
+            Angle::Degree => Some(360),
+            Angle::Gradian => Some(400),
+            Angle::Turn => Some(1),
+        }
+    }
+    // an angle num_parser gave back in radians, in this unit
+    pub fn to_unit(self, x: f64) -> f64 {
+        match self {
+            Angle::Radian => x,
+            Angle::Degree => x.to_degrees(),
+            Angle::Gradian => x * 400.0 / TAU,
+            Angle::Turn => x / TAU,
+        }
+    }
+    pub fn to_radians(self, x: f64) -> f64 {
+        match self {
+            Angle::Radian => x,
+            Angle::Degree => x.to_radians(),
+            Angle::Gradian => x * TAU / 400.0,
+            Angle::Turn => x * TAU,
+        }
+    }
+    // the unit num_parser is working in, gradians are worked out in degrees (see set())
+    pub fn of(unit: AngleUnit) -> Self {
+        match unit {
+            AngleUnit::Radian => Angle::Radian,
+            AngleUnit::Degree => Angle::Degree,
+            AngleUnit::Turn => Angle::Turn,
+        }
+    }
+    // Puts the context into the matching num_parser unit, gradians get worked out in degrees
+    pub fn set(self, context: &mut Context) {
+        context.angle_unit = match self {
+            Angle::Radian => AngleUnit::Radian,
+            Angle::Degree | Angle::Gradian => AngleUnit::Degree,
+            Angle::Turn => AngleUnit::Turn,
+        };
+    }
+}
+
+// deg(x), rad(x), grad(x) and turn(x) are an angle given in that unit, in the current one, and what suffixes turn into
+pub const UNIT_FUNCTIONS: [(&str, Angle); 4] = [
+    ("deg", Angle::Degree),
+    ("rad", Angle::Radian),
+    ("grad", Angle::Gradian),
+    ("turn", Angle::Turn),
+];
+
+pub fn register() {
+    builtin::add_built_in_function(Function::new("deg", deg, Arguments::Const(1)));
+    builtin::add_built_in_function(Function::new("rad", rad, Arguments::Const(1)));
+    builtin::add_built_in_function(Function::new("grad", grad, Arguments::Const(1)));
+    builtin::add_built_in_function(Function::new("turn", turn, Arguments::Const(1)));
+}
+// the &Vec signature is fixed by num_parser's Function type
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn deg(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    from_unit(Angle::Degree, arguments, context, depth)
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn rad(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    from_unit(Angle::Radian, arguments, context, depth)
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn grad(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    from_unit(Angle::Gradian, arguments, context, depth)
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn turn(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    from_unit(Angle::Turn, arguments, context, depth)
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn from_unit(
+    unit: Angle,
+    arguments: &Vec<Box<Expression>>,
+    context: &Context,
+    depth: u32,
+) -> EvalResult<Value> {
+    let x = arguments[0].eval(context, None, depth)?.as_float()?;
+    let current = Angle::of(context.angle_unit);
+    if unit == current {
+        return Ok(Value::Float(x));
+    }
+    Ok(Value::Float(current.to_unit(unit.to_radians(x))))
+}
+
+// num_parser's trig functions that take an angle (and cis from complex.rs), then the ones that give one back
+const TAKES_ANGLE: [&str; 7] = ["sin", "cos", "tan", "sinh", "cosh", "tanh", "cis"];
+const GIVES_ANGLE: [&str; 10] = [
+    "asin", "acos", "atan", "asinh", "acosh", "atanh", "deg", "rad", "grad", "turn",
+];
+
+// What actually gets worked out in a unit, with the query's trig calls converted in gradians, see in_gradians()
+pub fn converted(query: &str, unit: Angle) -> String {
+    match unit {
+        Angle::Gradian => in_gradians(query),
+        _ => query.to_string(),
+    }
+}
+// and a copy of the context to work it out with, every variable and function body converted the same way
+pub fn context_in(context: &Context, unit: Angle) -> Context {
+    let mut out = context.clone();
+    if unit != Angle::Gradian {
+        return out;
+    }
+    for (name, body) in &context.variables {
+        if let Some(body) = gradian_body(body, context) {
+            out.add_variable(name.clone(), body);
+        }
+    }
+    for (name, (params, body)) in &context.functions {
+        // the parameters have to be known names, or num_parser splits ones like ab into a*b
+        let mut scope = context.clone();
+        for param in params {
+            scope.add_variable(param.clone(), Box::new(Expression::Literal(Value::Int(0))));
+        }
+        if let Some(body) = gradian_body(body, &scope) {
+            out.add_function(name.clone(), params.clone(), body);
+        }
+    }
+    out
+}
+// None if there's nothing to convert, lists get written as [..] which only means something after stats::preprocess
+fn gradian_body(body: &Expression, context: &Context) -> Option<Box<Expression>> {
+    let text = func::parser(body);
+    let converted = in_gradians(&text);
+    if converted == text {
+        return None;
+    }
+    func::parse(&stats::preprocess(&converted), context).map(Box::new)
+}
+
+// In gradians the context is left in degrees and every trig call gets rewritten to convert,
+// sin(x) is sin((x)*9/10) and asin(x) is (asin(x)*10/9). Has to come after complex::preprocess so cis gets done too
+pub fn in_gradians(query: &str) -> String {
+    let mut chars: Vec<char> = query.chars().collect();
+    let mut at = 0;
+    while at < chars.len() {
+        // a function name is the whole word, so the sin in asin doesn't count
+        if at > 0 && chars[at - 1].is_alphabetic() {
+            at += 1;
+            continue;
+        }
+        let name_len = chars[at..].iter().take_while(|c| c.is_alphabetic()).count();
+        let name: String = chars[at..at + name_len].iter().collect();
+        let open = at + name_len;
+        let close = match chars.get(open) {
+            Some('(') => matching_bracket(&chars, open),
+            _ => None,
+        };
+        let (Some(close), true) = (
+            close,
+            TAKES_ANGLE.contains(&name.as_str()) || GIVES_ANGLE.contains(&name.as_str()),
+        ) else {
+            at += name_len.max(1);
+            continue;
+        };
+        // the argument is done first, it might have trig calls of its own
+        let inner: String = chars[open + 1..close].iter().collect();
+        let inner = in_gradians(&inner);
+        let replacement = if TAKES_ANGLE.contains(&name.as_str()) {
+            format!("{}(({})*9/10)", name, inner)
+        } else {
+            format!("({}({})*10/9)", name, inner)
+        };
+        let next = at + replacement.chars().count();
+        chars.splice(at..close + 1, replacement.chars());
+        at = next;
+    }
+    chars.into_iter().collect()
+}
+fn matching_bracket(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 {
+            return Some(i);
+        }
+    }
+    None
+}
+
+// Rewrites an angle with its own unit, like 30° or (pi/2)rad, as a call to that unit's function, deg(30) or rad(pi/2),
+// so 30° is always 30 degrees. The suffix goes straight after a number, name or bracket, with or without a space
+const SUFFIXES: [(&str, Angle); 5] = [
+    ("°", Angle::Degree),
+    ("deg", Angle::Degree),
+    ("rad", Angle::Radian),
+    ("grad", Angle::Gradian),
+    ("turn", Angle::Turn),
+];
+pub fn preprocess(query: &str) -> String {
+    let mut chars: Vec<char> = query.chars().collect();
+    let mut at = 0;
+    while at < chars.len() {
+        let Some((len, unit)) = suffix_at(&chars, at) else {
+            at += 1;
+            continue;
+        };
+        let Some(start) = operand_before(&chars, at) else {
+            at += len;
+            continue;
+        };
+        let operand: String = chars[start..at].iter().collect();
+        let name = UNIT_FUNCTIONS
+            .iter()
+            .find(|(_, u)| *u == unit)
+            .map_or("deg", |f| f.0);
+        let replacement = format!("{}({})", name, operand.trim());
+        let next = start + replacement.chars().count();
+        chars.splice(start..at + len, replacement.chars());
+        at = next;
+    }
+    chars.into_iter().collect()
+}
+fn suffix_at(chars: &[char], at: usize) -> Option<(usize, Angle)> {
+    SUFFIXES.iter().find_map(|(text, unit)| {
+        let len = text.chars().count();
+        let word = chars.get(at..at + len)?;
+        // a word suffix has to be the whole word, so gradient or turnip don't count,
+        // and isn't followed by a bracket, since then it's a call like 2*deg(30)
+        let joined = (at > 0 && chars[at - 1].is_alphabetic())
+            || chars
+                .get(at + len)
+                .is_some_and(|c| c.is_alphabetic() || *c == '(');
+        (word.iter().copied().eq(text.chars()) && !(joined && *text != "°")).then_some((len, *unit))
+    })
+}
+fn operand_before(chars: &[char], at: usize) -> Option<usize> {
+    let mut end = at;
+    while end > 0 && chars[end - 1] == ' ' {
+        end -= 1;
+    }
+    // only after something that can be a value, so "x = deg" or "f(rad)" are left alone
+    let last = *chars.get(end.checked_sub(1)?)?;
+    if !(last.is_alphanumeric() || last == ')' || last == '.') {
+        return None;
+    }
+    crate::app::complex::left_operand(chars, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+
+    fn eval_in(query: &str, unit: Angle) -> f64 {
+        let mut context = testing::context();
+        unit.set(&mut context);
+        let query = converted(&preprocess(query), unit);
+        num_parser::eval_with_static_context(&query, &context)
+            .unwrap()
+            .as_float()
+            .unwrap()
+    }
+
+    #[test]
+    fn suffixes_become_unit_functions() {
+        assert_eq!(preprocess("sin(30°)"), "sin(deg(30))");
+        assert_eq!(preprocess("cos(1.2rad)"), "cos(rad(1.2))");
+        assert_eq!(preprocess("x grad + 1 turn"), "grad(x) + turn(1)");
+        assert_eq!(preprocess("(pi/2)rad"), "rad((pi/2))");
+    }
+
+    #[test]
+    fn suffixes_have_to_be_whole_words_after_a_value() {
+        assert_eq!(preprocess("gradient + turnip"), "gradient + turnip");
+        assert_eq!(preprocess("2*deg(30)"), "2*deg(30)");
+        assert_eq!(preprocess("x = deg"), "x = deg");
+        assert_eq!(preprocess("f(rad)"), "f(rad)");
+    }
+
+    #[test]
+    fn trig_calls_get_converted_in_gradians() {
+        assert_eq!(in_gradians("sin(x)"), "sin((x)*9/10)");
+        assert_eq!(in_gradians("asin(x)"), "(asin(x)*10/9)");
+        assert_eq!(in_gradians("cos(asin(x))"), "cos(((asin(x)*10/9))*9/10)");
+        // only whole names, and only calls
+        assert_eq!(
+            in_gradians("sinx + basin(2) + sin"),
+            "sinx + basin(2) + sin"
+        );
+        assert_eq!(converted("sin(x)", Angle::Degree), "sin(x)");
+    }
+
+    #[test]
+    fn gradians_come_out_right() {
+        assert!((eval_in("sin(100)", Angle::Gradian) - 1.0).abs() < 1e-12);
+        assert!((eval_in("asin(1)", Angle::Gradian) - 100.0).abs() < 1e-12);
+        assert!((Angle::Gradian.to_unit(std::f64::consts::PI) - 200.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn a_value_with_its_own_unit_is_the_same_in_any_unit() {
+        for unit in [Angle::Radian, Angle::Degree, Angle::Gradian, Angle::Turn] {
+            assert!((eval_in("sin(30°)", unit) - 0.5).abs() < 1e-12);
+            assert!((eval_in("cos(0.5turn)", unit) + 1.0).abs() < 1e-12);
+        }
+        assert!((eval_in("deg(90)", Angle::Gradian) - 100.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stored_declarations_are_converted_when_used() {
+        let mut context = testing::context();
+        Angle::Gradian.set(&mut context);
+        num_parser::eval_with_mutable_context("f(x) = sin(x)", &mut context).unwrap();
+        let working = context_in(&context, Angle::Gradian);
+        let val = num_parser::eval_with_static_context("f(100)", &working).unwrap();
+        assert!((val.as_float().unwrap() - 1.0).abs() < 1e-12);
+        // and the stored one is left as it was
+        assert_eq!(
+            func::parser(&context.get_function("f").unwrap().1),
+            "sin(x)"
+        );
+    }
+
+    #[test]
+    fn parsing_the_options() {
+        assert_eq!(Angle::parse(" Gon "), Some(Angle::Gradian));
+        assert_eq!(Angle::parse("t"), Some(Angle::Turn));
+        assert_eq!(Angle::parse("furlongs"), None);
+    }
+}
@@ -0,0 +1,247 @@
+// complex numbers in polar form, for display and for input
+// num_parser only ever shows complex results as a+bi, so there's a setting to show them as r∠θ or r·e^(θi) instead
+// and for input r∠θ gets rewritten as (r)*cis(θ), cis being an extra built-in with cis(θ) = cos(θ) + i sin(θ)
+// θ is in the current angle unit for both (see angle.rs), except the exponential form which only makes sense in radians
+use crate::app::angle::Angle;
+use num::Complex;
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    settings::AngleUnit,
+    Context, EvalResult, Expression, Value,
+};
+use std::f64::consts::TAU;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ComplexFormat {
+    #[default]
+    Rectangular,
+    Polar,
+    Exponential,
+}
+impl ComplexFormat {
+    pub fn next(self) -> Self {
+        match self {
+            ComplexFormat::Rectangular => ComplexFormat::Polar,
+            ComplexFormat::Polar => ComplexFormat::Exponential,
+            ComplexFormat::Exponential => ComplexFormat::Rectangular,
+        }
+    }
+    // What the Output pane title shows
+    pub fn name(&self) -> &str {
+        match self {
+            ComplexFormat::Rectangular => "a+bi",
+            ComplexFormat::Polar => "r∠θ",
+            ComplexFormat::Exponential => "r·e^(θi)",
+        }
+    }
+}
+
+pub fn register() {
+    builtin::add_built_in_function(Function::new("cis", cis, Arguments::Const(1)));
+}
+// the &Vec signature is fixed by num_parser's Function type
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn cis(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let angle = arguments[0].eval(context, None, depth)?.as_float()?;
+    // in gradians the angle has already been turned into degrees, see angle::in_gradians
+    let radians = match context.angle_unit {
+        AngleUnit::Radian => angle,
+        AngleUnit::Degree => angle.to_radians(),
+        AngleUnit::Turn => angle * TAU,
+    };
+    Ok(Value::Complex(Complex::from_polar(1.0, radians)))
+}
+
+// Writes out a complex number, with num() doing the rounding/formatting of each part
+pub fn show(
+    c: Complex<f64>,
+    form: ComplexFormat,
+    unit: Angle,
+    num: impl Fn(f64) -> String,
+) -> String {
+    if c.im == 0.0 {
+        return num(c.re);
+    }
+    match form {
+        ComplexFormat::Rectangular => {
+            // laid out the same way num_parser's Display does it
+            let im = match num(c.im.abs()) {
+                one if one == "1" => "".to_string(),
+                im => im,
+            };
+            let sign = if c.im < 0.0 { "-" } else { "+" };
+            if c.re == 0.0 {
+                format!("{}{}i", if c.im < 0.0 { "-" } else { "" }, im)
+            } else {
+                format!("{}{}{}i", num(c.re), sign, im)
+            }
+        }
+        ComplexFormat::Polar => {
+            let (r, theta) = c.to_polar();
+            format!("{}∠{}{}", num(r), num(unit.to_unit(theta)), unit.suffix())
+        }
+        ComplexFormat::Exponential => {
+            let (r, theta) = c.to_polar();
+            format!("{}·e^({}i)", num(r), num(theta))
+        }
+    }
+}
+
+// Rewrites every r∠θ as (r)*cis(θ). Both sides are a number or name, or a bracket, or a function call,
+// so 2∠45+1 is (2)*cis(45)+1 and (1+1)∠f(x) is (1+1)*cis(f(x))
+pub fn preprocess(query: &str) -> String {
+    let mut chars: Vec<char> = query.chars().collect();
+    while let Some(at) = chars.iter().position(|c| *c == '∠') {
+        let (Some(start), Some(end)) = (left_operand(&chars, at), right_operand(&chars, at)) else {
+            // leave it for num_parser to complain about
+            break;
+        };
+        let left: String = chars[start..at].iter().collect();
+        let right: String = chars[at + 1..end].iter().collect();
+        let replacement = format!("({})*cis({})", left.trim(), right.trim());
+        chars.splice(start..end, replacement.chars());
+    }
+    chars.into_iter().collect()
+}
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '.'
+}
+pub fn left_operand(chars: &[char], at: usize) -> Option<usize> {
+    let mut i = at;
+    while i > 0 && chars[i - 1] == ' ' {
+        i -= 1;
+    }
+    if i > 0 && chars[i - 1] == ')' {
+        // back to the matching bracket, then over a function name if there is one
+        let mut depth = 0;
+        loop {
+            i = i.checked_sub(1)?;
+            match chars[i] {
+                ')' => depth += 1,
+                '(' => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+    while i > 0 && is_word(chars[i - 1]) {
+        i -= 1;
+    }
+    (i < at && chars[i..at].iter().any(|c| *c != ' ')).then_some(i)
+}
+fn right_operand(chars: &[char], at: usize) -> Option<usize> {
+    let mut i = at + 1;
+    while i < chars.len() && chars[i] == ' ' {
+        i += 1;
+    }
+    if i < chars.len() && chars[i] == '-' {
+        i += 1;
+    }
+    let word_start = i;
+    while i < chars.len() && is_word(chars[i]) {
+        i += 1;
+    }
+    if i < chars.len() && chars[i] == '(' {
+        let mut depth = 0;
+        loop {
+            match chars.get(i)? {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => (),
+            }
+            i += 1;
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+    (i > word_start).then_some(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+
+    fn show_in(c: Complex<f64>, form: ComplexFormat, unit: Angle) -> String {
+        show(c, form, unit, |x| {
+            Value::Float(x)
+                .round(num_parser::settings::Rounding::Round(3))
+                .to_string()
+        })
+    }
+
+    #[test]
+    fn polar_input() {
+        assert_eq!(preprocess("2∠45"), "(2)*cis(45)");
+        assert_eq!(preprocess("2∠45+1"), "(2)*cis(45)+1");
+        assert_eq!(preprocess("(1+1) ∠ f(x)"), "((1+1))*cis(f(x))");
+        assert_eq!(preprocess("r∠-pi"), "(r)*cis(-pi)");
+        assert_eq!(preprocess("sqrt(2)∠1"), "(sqrt(2))*cis(1)");
+        // nothing on one side is left for num_parser to complain about
+        assert_eq!(preprocess("∠45"), "∠45");
+        assert_eq!(preprocess("2∠"), "2∠");
+    }
+
+    #[test]
+    fn cis_follows_the_angle_unit() {
+        let mut context = testing::context();
+        let z = num_parser::eval_with_static_context("(2)*cis(pi/2)", &context)
+            .unwrap()
+            .as_complex()
+            .unwrap();
+        assert!(z.re.abs() < 1e-12 && (z.im - 2.0).abs() < 1e-12);
+        context.angle_unit = AngleUnit::Degree;
+        let z = num_parser::eval_with_static_context(&preprocess("1∠180"), &context)
+            .unwrap()
+            .as_complex()
+            .unwrap();
+        assert!((z.re + 1.0).abs() < 1e-12 && z.im.abs() < 1e-12);
+    }
+
+    #[test]
+    fn polar_and_exponential_output() {
+        let z = Complex::new(1.0, 1.0);
+        assert_eq!(show_in(z, ComplexFormat::Rectangular, Angle::Radian), "1+i");
+        assert_eq!(show_in(z, ComplexFormat::Polar, Angle::Degree), "1.414∠45°");
+        assert_eq!(
+            show_in(z, ComplexFormat::Polar, Angle::Radian),
+            "1.414∠0.785"
+        );
+        assert_eq!(
+            show_in(z, ComplexFormat::Polar, Angle::Turn),
+            "1.414∠0.125 turn"
+        );
+        assert_eq!(
+            show_in(z, ComplexFormat::Polar, Angle::Gradian),
+            "1.414∠50 grad"
+        );
+        // exponential is always in radians
+        assert_eq!(
+            show_in(z, ComplexFormat::Exponential, Angle::Degree),
+            "1.414·e^(0.785i)"
+        );
+        // real numbers look the same whatever the form
+        assert_eq!(
+            show_in(Complex::new(-2.0, 0.0), ComplexFormat::Polar, Angle::Degree),
+            "-2"
+        );
+        assert_eq!(
+            show_in(
+                Complex::new(0.0, -3.0),
+                ComplexFormat::Rectangular,
+                Angle::Radian
+            ),
+            "-3i"
+        );
+    }
+
+    #[test]
+    fn forms_cycle() {
+        let form = ComplexFormat::default();
+        assert_eq!(form.next().next().next(), form);
+        assert_eq!(form.next().name(), "r∠θ");
+    }
+}
@@ -0,0 +1,61 @@
+// Settings kept between runs, as lines of key = value in $XDG_CONFIG_HOME/rust_calc/config (~/.config/rust_calc/config
+// if that isn't set). Lines that don't look like that, e.g. # comments, are ignored, and a missing file is just no settings
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+fn path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("rust_calc").join("config"))
+}
+pub fn load() -> HashMap<String, String> {
+    let Some(contents) = path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return HashMap::new();
+    };
+    parse(&contents)
+}
+fn parse(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter(|(key, _)| !key.trim_start().starts_with('#'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+// only changes the keys given, anything else already in the file stays
+pub fn save(values: BTreeMap<&str, String>) -> io::Result<()> {
+    let path = path().ok_or(io::Error::other("there's no home directory to put it in"))?;
+    let mut all: BTreeMap<String, String> = load().into_iter().collect();
+    for (key, value) in values {
+        all.insert(key.to_string(), value);
+    }
+    let contents: String = all
+        .iter()
+        .map(|(key, value)| format!("{} = {}\n", key, value))
+        .collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_value_lines() {
+        let values = parse(
+            "# layout\nleft_width = 30\n  show_history=false  \nnonsense\n#right_width = 10\n",
+        );
+        assert_eq!(values.len(), 2);
+        assert_eq!(values["left_width"], "30");
+        assert_eq!(values["show_history"], "false");
+        // only the first = splits
+        assert_eq!(parse("a = b = c")["a"], "b = c");
+    }
+}
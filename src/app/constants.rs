@@ -0,0 +1,166 @@
+// a library of physical and mathematical constants, loaded into the context as ordinary variables
+// num_parser only ships pi, e, tau and phi. These get stored with add_variable, which also means multi-letter names
+// like hbar and NA work, since num_parser matches names it already knows before splitting the rest into letters
+// While the library is on the names are protected, so "c = 3" is an error rather than quietly replacing the speed of light,
+// and they get their own Constants pane rather than filling up User Variables. Turning it off frees the names up again.
+// The units are used by units.rs, so "c in km/h" and "me c^2 in MeV" work too
+use crate::app::func;
+use num_parser::{Context, Expression, Value};
+
+// name, value, SI units (empty for plain numbers), and what it is
+const LIBRARY: &[(&str, f64, &str, &str)] = &[
+    ("c", 299792458.0, "m/s", "speed of light"),
+    ("planck", 6.62607015e-34, "J s", "Planck constant"),
+    ("hbar", 1.054571817e-34, "J s", "reduced Planck constant"),
+    ("G", 6.6743e-11, "m^3/(kg s^2)", "gravitational constant"),
+    ("gn", 9.80665, "m/s^2", "standard gravity"),
+    ("NA", 6.02214076e23, "1/mol", "Avogadro constant"),
+    ("kB", 1.380649e-23, "J/K", "Boltzmann constant"),
+    ("R", 8.314462618, "J/(mol K)", "gas constant"),
+    ("qe", 1.602176634e-19, "C", "elementary charge"),
+    ("me", 9.1093837015e-31, "kg", "electron mass"),
+    ("mp", 1.67262192369e-27, "kg", "proton mass"),
+    ("mn", 1.67492749804e-27, "kg", "neutron mass"),
+    ("amu", 1.6605390666e-27, "kg", "atomic mass unit"),
+    ("eps", 8.8541878128e-12, "F/m", "vacuum permittivity"),
+    ("mu", 1.25663706212e-6, "N/A^2", "vacuum permeability"),
+    (
+        "sigma",
+        5.670374419e-8,
+        "W/(m^2 K^4)",
+        "Stefan-Boltzmann constant",
+    ),
+    ("alpha", 7.2973525693e-3, "", "fine-structure constant"),
+    ("au", 1.495978707e11, "m", "astronomical unit"),
+    // not plain gamma, that's the gamma function in discrete.rs
+    (
+        "eulergamma",
+        0.5772156649015329,
+        "",
+        "Euler-Mascheroni constant",
+    ),
+];
+// num_parser's own constants, listed in the pane too so everything's in one place
+const BUILT_IN: &[(&str, &str)] = &[
+    ("pi", "3.14159265"),
+    ("e", "2.71828183"),
+    ("tau", "6.28318531"),
+    ("phi", "1.61803399 (golden ratio)"),
+];
+
+pub fn find(name: &str) -> Option<(f64, &'static str)> {
+    LIBRARY
+        .iter()
+        .find(|c| c.0 == name)
+        .map(|(_, value, units, _)| (*value, *units))
+}
+
+pub fn load(context: &mut Context) {
+    for (name, value, _, _) in LIBRARY {
+        context.add_variable(
+            name.to_string(),
+            Box::new(Expression::Literal(Value::Float(*value))),
+        );
+    }
+}
+pub fn unload(context: &mut Context) {
+    for (name, _, _, _) in LIBRARY {
+        context.variables.remove(*name);
+    }
+}
+
+// An error message if the query would redefine one of the constants, e.g. "c = 3" or "h(x) = x^2"
+pub fn check_declaration(query: &str) -> Option<String> {
+    let chars: Vec<char> = query.chars().collect();
+    let name: String = chars[..func::assignment(query)?].iter().collect();
+    let name = name.split('(').next().unwrap_or("").trim();
+    find(name)?;
+    Some(format!(
+        "'{}' is in the constants library, turn it off in Options to use the name yourself",
+        name
+    ))
+}
+
+// The lines of the Constants pane
+pub fn to_strings() -> Vec<String> {
+    let library = LIBRARY.iter().map(|(name, value, units, what)| {
+        format!("\n{} = {} {} ({})", name, short(*value), units, what)
+    });
+    let built_in = BUILT_IN
+        .iter()
+        .map(|(name, value)| format!("\n{} = {}", name, value));
+    library.chain(built_in).collect()
+}
+fn short(x: f64) -> String {
+    // Display on an f64 never uses an exponent, so h would come out with 33 zeros
+    if (1e-4..1e9).contains(&x.abs()) {
+        x.to_string()
+    } else {
+        format!("{:e}", x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{testing, units, DisplaySettings};
+
+    #[test]
+    fn declarations_cant_overwrite_constants() {
+        assert!(check_declaration("c = 3").is_some());
+        assert!(check_declaration("hbar=1").is_some());
+        assert!(check_declaration("planck(x) = x^2").is_some());
+        assert!(check_declaration("h = 2").is_none());
+        assert!(check_declaration("cc = 3").is_none());
+        assert!(check_declaration("f(c) = c^2").is_none());
+        // comparisons aren't declarations
+        assert!(check_declaration("c == 3").is_none());
+        assert!(check_declaration("c * 2").is_none());
+    }
+
+    #[test]
+    fn load_and_unload() {
+        let mut context = Context::default();
+        load(&mut context);
+        let speed = num_parser::eval_with_static_context("2 * c", &context).unwrap();
+        assert_eq!(speed.as_float().unwrap(), 599584916.0);
+        assert!(num_parser::eval_with_static_context("NA * kB", &context).is_ok());
+        unload(&mut context);
+        assert!(num_parser::eval_with_static_context("hbar", &context).is_err());
+        assert!(context.variables.is_empty());
+    }
+
+    #[test]
+    fn eulergamma_leaves_the_gamma_function_alone() {
+        let mut context = testing::context();
+        load(&mut context);
+        let eval = |query| num_parser::eval_with_static_context(query, &context);
+        assert_eq!(eval("gamma(5)").unwrap(), Value::Float(24.0));
+        let euler = eval("eulergamma").unwrap().as_float().unwrap();
+        assert!((euler - 0.5772156649).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pane_keeps_tiny_values_readable() {
+        assert_eq!(short(299792458.0), "299792458");
+        assert_eq!(short(6.62607015e-34), "6.62607015e-34");
+        assert_eq!(short(6.02214076e23), "6.02214076e23");
+        assert_eq!(to_strings().len(), LIBRARY.len() + BUILT_IN.len());
+    }
+
+    #[test]
+    fn constants_carry_their_units() {
+        let context = Context::default();
+        let speed = units::eval("2 c in m/s", &context, &DisplaySettings::default());
+        assert_eq!(speed, Some(Ok("599584916 m/s".to_string())));
+        // with the library off c is just an unknown name
+        let off = DisplaySettings {
+            constants: false,
+            ..DisplaySettings::default()
+        };
+        assert!(!matches!(
+            units::eval("c in km/h", &context, &off),
+            Some(Ok(_))
+        ));
+    }
+}
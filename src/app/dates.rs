@@ -0,0 +1,622 @@
+// dates, times and durations, e.g. "2026-10-17 + 90 days", "now - 2026-01-01 in hours" or "3h 25min * 4"
+// like units.rs this is its own little evaluator, since num_parser would read 2026-10-17 as a subtraction.
+// Queries only come here if they have a date in them (YYYY-MM-DD, optionally with a time like 2026-10-17 14:30
+// or 2026-10-17T14:30:00), now or today, or durations and nothing else that looks like a unit, like 3 days + 4 hours
+// Everything's in UTC and counted in seconds, dates from the start of 1970 like the system clock
+// - a date plus or minus a duration is a date, and one date minus another is a duration
+// - durations can be multiplied or divided by numbers, and a duration over a duration is a plain number
+// - "in hours" (or seconds, minutes, days, weeks) turns a duration into a number of them
+// - durations are shown with the same names as units.rs, s, min, h, d and wk, and m is metres rather than minutes
+//   like it is there. Months and years aren't fixed lengths so they aren't allowed
+// anything else, variables and functions and so on, still gets worked out by num_parser and has to be a real number
+use crate::app::{
+    format,
+    lib::{Answer, DisplaySettings},
+};
+use num_parser::{settings::Rounding, Context, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY: f64 = 86400.0;
+// the name to show, the names that can be typed, and its length in seconds. Longest first for display
+const DURATIONS: [(&str, &[&str], f64); 5] = [
+    ("wk", &["w", "wk", "week", "weeks"], 7.0 * DAY),
+    ("d", &["d", "day", "days"], DAY),
+    ("h", &["h", "hr", "hour", "hours"], 3600.0),
+    ("min", &["min", "mins", "minute", "minutes"], 60.0),
+    ("s", &["s", "sec", "secs", "second", "seconds"], 1.0),
+];
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+fn duration(name: &str) -> Option<f64> {
+    DURATIONS
+        .iter()
+        .find(|(_, names, _)| names.contains(&name))
+        .map(|d| d.2)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Time {
+    Number(f64),
+    // seconds since 1970-01-01 00:00 UTC
+    Date(f64),
+    // seconds
+    Duration(f64),
+}
+impl Time {
+    fn kind(&self) -> &str {
+        match self {
+            Time::Number(_) => "a number",
+            Time::Date(_) => "a date",
+            Time::Duration(_) => "a duration",
+        }
+    }
+    fn add(self, other: Time, sign: f64) -> Result<Time, String> {
+        match (self, other) {
+            (Time::Number(a), Time::Number(b)) => Ok(Time::Number(a + sign * b)),
+            (Time::Duration(a), Time::Duration(b)) => Ok(Time::Duration(a + sign * b)),
+            (Time::Date(a), Time::Duration(b)) => Ok(Time::Date(a + sign * b)),
+            (Time::Duration(a), Time::Date(b)) if sign > 0.0 => Ok(Time::Date(a + b)),
+            (Time::Date(a), Time::Date(b)) if sign < 0.0 => Ok(Time::Duration(a - b)),
+            (a, b) => Err(format!(
+                "Can't {} {} and {}",
+                if sign > 0.0 { "add" } else { "subtract" },
+                a.kind(),
+                b.kind()
+            )),
+        }
+    }
+    fn times(self, other: Time, divide: bool) -> Result<Time, String> {
+        let op = |a: f64, b: f64| if divide { a / b } else { a * b };
+        match (self, other) {
+            (Time::Number(a), Time::Number(b)) => Ok(Time::Number(op(a, b))),
+            (Time::Duration(a), Time::Number(b)) => Ok(Time::Duration(op(a, b))),
+            (Time::Number(a), Time::Duration(b)) if !divide => Ok(Time::Duration(a * b)),
+            (Time::Duration(a), Time::Duration(b)) if divide => Ok(Time::Number(a / b)),
+            (a, b) => Err(format!(
+                "Can't {} {} by {}",
+                if divide { "divide" } else { "multiply" },
+                a.kind(),
+                b.kind()
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Date(f64),
+    // looks like a date but isn't one, like 2026-02-30, with what's wrong with it
+    BadDate(String),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<(usize, char)> = query.char_indices().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if let Some((len, date)) = date_literal(&chars[i..]) {
+            i += len;
+            date.map_or_else(Token::BadDate, Token::Date)
+        } else if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().map(|c| c.1).collect();
+            Token::Num(
+                text.parse()
+                    .map_err(|_| format!("'{}' isn't a number", text))?,
+            )
+        } else if c.is_alphabetic() {
+            while i < chars.len() && chars[i].1.is_alphabetic() {
+                i += 1;
+            }
+            Token::Ident(chars[start..i].iter().map(|c| c.1).collect())
+        } else {
+            i += 1;
+            match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                '+' | '-' | '*' | '/' | '^' => Token::Op(c),
+                _ => return Err(format!("'{}' can't be used with dates", c)),
+            }
+        };
+        tokens.push((at, token));
+    }
+    Ok(tokens)
+}
+// YYYY-MM-DD with an optional HH:MM or HH:MM:SS after a space or a T.
+// Gives back how many chars it took up, and an error if it looks like a date but isn't one, like 2026-02-30
+fn date_literal(chars: &[(usize, char)]) -> Option<(usize, Result<f64, String>)> {
+    let text: String = chars.iter().map(|c| c.1).collect();
+    let digits = |from: usize, len: usize| -> Option<i64> {
+        let part = text.get(from..from + len)?;
+        part.chars()
+            .all(|c| c.is_ascii_digit())
+            .then(|| part.parse().ok())?
+    };
+    let (year, month, day) = (digits(0, 4)?, digits(5, 2)?, digits(8, 2)?);
+    if text.get(4..5) != Some("-") || text.get(7..8) != Some("-") || digits(10, 1).is_some() {
+        return None;
+    }
+    let mut len = 10;
+    let (mut hour, mut minute, mut second) = (0, 0, 0);
+    let separator = text.get(10..11).is_some_and(|s| s == " " || s == "T");
+    if let (true, Some(h), Some(":"), Some(m)) =
+        (separator, digits(11, 2), text.get(13..14), digits(14, 2))
+    {
+        (len, hour, minute) = (16, h, m);
+        if let (Some(":"), Some(s)) = (text.get(16..17), digits(17, 2)) {
+            (len, second) = (19, s);
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return Some((len, Err(format!("{} isn't a time of day", &text[11..len]))));
+        }
+    }
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return Some((len, Err(format!("{} isn't a date", &text[..10]))));
+    }
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some((len, Ok(seconds as f64)))
+}
+
+// None if the query has nothing to do with dates, so units.rs or num_parser should handle it
+// otherwise the answer, or an error message
+pub fn eval(
+    query: &str,
+    context: &Context,
+    display: &DisplaySettings,
+) -> Option<Result<Answer, String>> {
+    // declarations and comparisons are left to num_parser
+    if query.contains('=') || query.contains('<') || query.contains('>') {
+        return None;
+    }
+    let tokens = tokenize(query).ok()?;
+    let is_unit = |i: usize| matches!(tokens.get(i), Some((_, Token::Ident(name))) if duration(name).is_some());
+    let is_num = |i: usize| matches!(tokens.get(i), Some((_, Token::Num(_))));
+    let is_call = |i: usize| tokens.get(i + 1).map(|t| &t.1) == Some(&Token::Open);
+    let mentions_date = tokens
+        .iter()
+        .enumerate()
+        .any(|(i, (_, token))| match token {
+            Token::Date(_) | Token::BadDate(_) => true,
+            Token::Ident(name) => is_clock(name, context) && !is_call(i),
+            _ => false,
+        });
+    // or durations, 3 days + 4 hours or 10 days in weeks, with no other units for units.rs to deal with.
+    // Anything else has to be a variable or a function call
+    let only_durations = (0..tokens.len()).any(|i| is_num(i) && is_unit(i + 1))
+        && tokens
+            .iter()
+            .enumerate()
+            .all(|(i, (_, token))| match token {
+                Token::Ident(name) => {
+                    is_unit(i)
+                        || name == "in"
+                        || name == "to"
+                        || context.get_var(name).is_some()
+                        || is_call(i)
+                }
+                _ => true,
+            });
+    if !(mentions_date || only_durations) {
+        return None;
+    }
+    let mut raw = context.clone();
+    raw.rounding = Rounding::NoRounding;
+    let shown = |x: f64| format::number(x, context.rounding, display.format);
+    Some(convert(query, &tokens, &raw, shown))
+}
+// now and today, unless they've been taken as variable names
+fn is_clock(name: &str, context: &Context) -> bool {
+    (name == "now" || name == "today") && context.get_var(name).is_none()
+}
+
+fn convert(
+    query: &str,
+    tokens: &[(usize, Token)],
+    context: &Context,
+    round: impl Fn(f64) -> String,
+) -> Result<Answer, String> {
+    // "in hours" at the end gives a duration as a number of hours
+    let split = tokens
+        .iter()
+        .position(|(_, t)| matches!(t, Token::Ident(name) if name == "in" || name == "to"));
+    let (tokens, target) = match split {
+        Some(i) => (&tokens[..i], Some(&tokens[i + 1..])),
+        None => (tokens, None),
+    };
+    let result = parse_all(query, tokens, context)?;
+    match (result, target) {
+        (Time::Number(x), None) => Ok(Answer::Text(round(x))),
+        // dates are left out of digit grouping, 2,026-10-17 would be silly
+        (Time::Date(x), None) => Ok(Answer::Verbatim(show_date(x))),
+        (Time::Duration(x), None) => Ok(Answer::Text(show_duration(x, round))),
+        (Time::Duration(x), Some([(_, Token::Ident(name))])) => {
+            let (shown, _, size) = DURATIONS
+                .iter()
+                .find(|(_, names, _)| names.contains(&name.as_str()))
+                .ok_or_else(|| {
+                    format!("'{}' isn't seconds, minutes, hours, days or weeks", name)
+                })?;
+            Ok(Answer::Text(format!("{} {}", round(x / size), shown)))
+        }
+        (Time::Duration(_), _) => {
+            Err("Give a single unit to convert to, like in hours".to_string())
+        }
+        (other, Some(_)) => Err(format!(
+            "Only durations can be converted, not {}",
+            other.kind()
+        )),
+    }
+}
+
+fn parse_all(query: &str, tokens: &[(usize, Token)], context: &Context) -> Result<Time, String> {
+    let mut parser = Parser {
+        query,
+        tokens,
+        pos: 0,
+        context,
+    };
+    let result = parser.sum()?;
+    match tokens.get(parser.pos) {
+        None => Ok(result),
+        Some((at, _)) => Err(format!("Didn't expect '{}'", &query[*at..])),
+    }
+}
+
+// Recursive descent in the same shape as units.rs:
+// sum: a + b - c, product: a * b / c, juxtaposed: a b c, unary: -a, power: a^b, and then atom
+// Durations written next to each other add up, 3h 25min is 3h + 25min, anything else next to each other multiplies
+struct Parser<'a> {
+    query: &'a str,
+    tokens: &'a [(usize, Token)],
+    pos: usize,
+    context: &'a Context,
+}
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.1)
+    }
+    fn next(&mut self) -> Option<Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1).map(|t| t.1.clone())
+    }
+    // the query text from token `from` up to but not including the current one
+    fn text_since(&self, from: usize) -> &str {
+        let start = self.tokens[from].0;
+        let end = self.tokens.get(self.pos).map_or(self.query.len(), |t| t.0);
+        &self.query[start..end]
+    }
+    fn sum(&mut self) -> Result<Time, String> {
+        let mut total = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let sign = if op == '+' { 1.0 } else { -1.0 };
+            total = total.add(self.product()?, sign)?;
+        }
+        Ok(total)
+    }
+    fn product(&mut self) -> Result<Time, String> {
+        let mut total = self.juxtaposed()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.next();
+            total = total.times(self.juxtaposed()?, op == '/')?;
+        }
+        Ok(total)
+    }
+    fn juxtaposed(&mut self) -> Result<Time, String> {
+        let mut total = self.unary()?;
+        while matches!(
+            self.peek(),
+            Some(
+                Token::Num(_) | Token::Date(_) | Token::BadDate(_) | Token::Ident(_) | Token::Open
+            )
+        ) && !matches!(self.peek(), Some(Token::Ident(name)) if name == "in" || name == "to")
+        {
+            let next = self.unary()?;
+            total = match (total, next) {
+                (Time::Duration(_), Time::Duration(_)) => total.add(next, 1.0)?,
+                _ => total.times(next, false)?,
+            };
+        }
+        Ok(total)
+    }
+    fn unary(&mut self) -> Result<Time, String> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.next();
+                Time::Number(-1.0).times(self.unary()?, false)
+            }
+            Some(Token::Op('+')) => {
+                self.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+    fn power(&mut self) -> Result<Time, String> {
+        let base = self.atom()?;
+        if self.peek() != Some(&Token::Op('^')) {
+            return Ok(base);
+        }
+        self.next();
+        match (base, self.unary()?) {
+            (Time::Number(a), Time::Number(b)) => Ok(Time::Number(a.powf(b))),
+            (a, b) => Err(format!(
+                "Can't raise {} to the power of {}",
+                a.kind(),
+                b.kind()
+            )),
+        }
+    }
+    fn atom(&mut self) -> Result<Time, String> {
+        let start = self.pos;
+        match self.next() {
+            // 90 days, or 3h with the unit stuck on
+            Some(Token::Num(n)) => match self.peek() {
+                Some(Token::Ident(name)) if duration(name).is_some() => {
+                    let size = duration(name).unwrap_or(1.0);
+                    self.next();
+                    Ok(Time::Duration(n * size))
+                }
+                _ => Ok(Time::Number(n)),
+            },
+            Some(Token::Date(date)) => Ok(Time::Date(date)),
+            Some(Token::BadDate(msg)) => Err(msg),
+            Some(Token::Open) => {
+                let inner = self.sum()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("Missing a closing bracket".to_string()),
+                }
+            }
+            Some(Token::Ident(_)) if self.peek() == Some(&Token::Open) => {
+                self.next();
+                self.skip_brackets()?;
+                self.number(start)
+            }
+            Some(Token::Ident(name)) if is_clock(&name, self.context) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0.0, |d| d.as_secs_f64());
+                match name.as_str() {
+                    "today" => Ok(Time::Date((now / DAY).floor() * DAY)),
+                    _ => Ok(Time::Date(now.floor())),
+                }
+            }
+            // a unit on its own is one of them, "now + day", unless it's a variable
+            Some(Token::Ident(name))
+                if self.context.get_var(&name).is_none() && duration(&name).is_some() =>
+            {
+                Ok(Time::Duration(duration(&name).unwrap_or(1.0)))
+            }
+            Some(Token::Ident(_)) => self.number(start),
+            Some(_) => Err(format!("Didn't expect '{}'", self.text_since(start))),
+            None => Err("The query ends too soon".to_string()),
+        }
+    }
+    fn skip_brackets(&mut self) -> Result<(), String> {
+        // just after an opening bracket, skips to just after its partner
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Open) => depth += 1,
+                Some(Token::Close) => depth -= 1,
+                Some(_) => (),
+                None => return Err("Missing a closing bracket".to_string()),
+            }
+        }
+        Ok(())
+    }
+    // Hands the text of the tokens since `start` to num_parser, which has to give back a real number
+    fn number(&self, start: usize) -> Result<Time, String> {
+        let text = self.text_since(start).trim();
+        match num_parser::eval_with_static_context(text, self.context) {
+            Ok(Value::Int(n)) => Ok(Time::Number(n as f64)),
+            Ok(Value::Float(x)) => Ok(Time::Number(x)),
+            Ok(other) => Err(format!("'{}' = {} isn't a real number", text, other)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+// 2027-01-15 (Friday), with the time as well if it isn't midnight
+fn show_date(x: f64) -> String {
+    let seconds = x.round() as i64;
+    let days = seconds.div_euclid(86400);
+    let time = seconds.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let weekday = WEEKDAYS[(days + 4).rem_euclid(7) as usize];
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+    match time {
+        0 => format!("{} ({})", date, weekday),
+        _ => format!(
+            "{} {:02}:{:02}:{:02} ({})",
+            date,
+            time / 3600,
+            time / 60 % 60,
+            time % 60,
+            weekday
+        ),
+    }
+}
+// 2 d 3 h 25 min, weeks get left as days since 90 d reads better than 12 wk 6 d
+fn show_duration(x: f64, round: impl Fn(f64) -> String) -> String {
+    if x == 0.0 {
+        return "0 s".to_string();
+    }
+    let sign = if x < 0.0 { "minus " } else { "" };
+    let mut left = x.abs();
+    let mut parts = vec![];
+    for (name, _, size) in &DURATIONS[1..4] {
+        let whole = (left / size).floor();
+        // float error like 2.9999999 hours shouldn't turn into 2 hours 60 minutes
+        let whole = if (left - whole * size - size).abs() < 1e-6 {
+            whole + 1.0
+        } else {
+            whole
+        };
+        left = (left - whole * size).max(0.0);
+        if whole > 0.0 {
+            parts.push(format!("{} {}", whole, name));
+        }
+    }
+    if left > 1e-6 {
+        parts.push(format!("{} s", round(left)));
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+// Howard Hinnant's algorithms for days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dates(query: &str) -> Option<Result<String, String>> {
+        let answer = eval(query, &Context::default(), &DisplaySettings::default())?;
+        Some(answer.map(|a| match a {
+            Answer::Text(text) | Answer::Verbatim(text) => text,
+            Answer::Value(val) => val.to_string(),
+        }))
+    }
+    fn answer(query: &str) -> String {
+        dates(query).unwrap().unwrap()
+    }
+
+    #[test]
+    fn date_literals() {
+        let date = |text: &str| tokenize(text).unwrap()[0].1.clone();
+        assert_eq!(date("1970-01-02"), Token::Date(DAY));
+        assert_eq!(date("1970-01-01 01:02"), Token::Date(3720.0));
+        assert_eq!(date("1970-01-01T00:00:30"), Token::Date(30.0));
+        assert_eq!(
+            date("2026-02-30"),
+            Token::BadDate("2026-02-30 isn't a date".to_string())
+        );
+        assert_eq!(
+            date("2026-01-01 24:00"),
+            Token::BadDate("24:00 isn't a time of day".to_string())
+        );
+        // too many digits, so it's just a subtraction
+        assert_eq!(date("2026-01-011"), Token::Num(2026.0));
+    }
+
+    #[test]
+    fn the_rest_of_the_tokens() {
+        let tokens: Vec<Token> = tokenize("3h 25min * (2, x)")
+            .unwrap()
+            .into_iter()
+            .map(|(_, t)| t)
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Num(3.0),
+                Token::Ident("h".to_string()),
+                Token::Num(25.0),
+                Token::Ident("min".to_string()),
+                Token::Op('*'),
+                Token::Open,
+                Token::Num(2.0),
+                Token::Comma,
+                Token::Ident("x".to_string()),
+                Token::Close,
+            ]
+        );
+        assert!(tokenize("2026-01-01 % 2").is_err());
+    }
+
+    #[test]
+    fn header_examples() {
+        assert_eq!(answer("2026-10-17 + 90 days"), "2027-01-15 (Friday)");
+        assert_eq!(answer("3h 25min * 4"), "13 h 40 min");
+        assert!(dates("now - 2026-01-01 in hours").unwrap().is_ok());
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(answer("2026-03-01 - 2026-02-01"), "28 d");
+        assert_eq!(answer("2024-03-01 - 2024-02-01 in weeks"), "4.14285714 wk");
+        assert_eq!(answer("10 days in weeks"), "1.42857143 wk");
+        assert_eq!(
+            answer("2026-01-01 12:30 + 90 minutes"),
+            "2026-01-01 14:00:00 (Thursday)"
+        );
+        assert_eq!(answer("(2026-01-02 - 2026-01-01) / 1h"), "24");
+        assert_eq!(answer("2026-01-01 - 2026-01-02"), "minus 1 d");
+    }
+
+    #[test]
+    fn things_that_dont_make_sense() {
+        assert!(dates("2026-01-01 + 2026-01-02").unwrap().is_err());
+        assert!(dates("2026-01-01 * 2").unwrap().is_err());
+        assert!(dates("2026-01-01 in days").unwrap().is_err());
+        assert!(dates("2026-02-30 + 1 day").unwrap().is_err());
+    }
+
+    #[test]
+    fn left_to_num_parser() {
+        assert_eq!(dates("3 - 2"), None);
+        assert_eq!(dates("2026 - 10 - 17"), None);
+        assert_eq!(dates("x = 2026-01-01"), None);
+        // metres, and anything else with a unit that isn't a duration, are for units.rs
+        assert_eq!(dates("1 m in s"), None);
+        assert_eq!(dates("60 km/h * 2 h"), None);
+    }
+
+    #[test]
+    fn durations_on_their_own() {
+        assert_eq!(answer("3 days + 4 hours"), "3 d 4 h");
+        assert_eq!(answer("90 minutes - 1 h"), "30 min");
+        // same names as units.rs gives
+        assert_eq!(answer("5 min in s"), "300 s");
+        assert_eq!(answer("2 d in h"), "48 h");
+    }
+}
@@ -0,0 +1,575 @@
+// random numbers, counting and whole number functions
+//   rand() a random number from 0 up to 1, rand(a, b) one from a up to b, randint(a, b) a whole one from a to b inclusive
+//   seed(n) restarts the random numbers from n, so the same seed always gives the same ones
+//   nCr(n, r) and nPr(n, r) choose and permute, factorial(n) or n!, gamma(x) and lgamma(x) its logarithm
+//   gcd and lcm of any number of integers, isprime(n), and factor(n) the list of prime factors, factor(360) = [2,2,2,3,3,5]
+// Whole number answers come back as num_parser Ints so they show exactly, past i64 they fall back to floats,
+// which the Fixed format writes in scientific notation rather than as digits that look exact but aren't (see format.rs)
+// num_parser already has a rand(a, b) of its own that can't be seeded or replaced (see angle.rs), so rand gets
+// rewritten to random before it sees the query, see preprocess()
+use crate::app::{
+    angle::{self, Angle},
+    complex, worker,
+};
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    Context, ErrorType, EvalResult, Expression, Value,
+};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// the random number generator's state, None until the first number is asked for or seed() is used
+static STATE: Mutex<Option<u64>> = Mutex::new(None);
+
+pub fn register() {
+    let functions: [(&'static str, Call, Arguments); 12] = [
+        ("random", random, Arguments::Dynamic),
+        ("randint", randint, Arguments::Const(2)),
+        ("seed", seed, Arguments::Const(1)),
+        ("nCr", ncr, Arguments::Const(2)),
+        ("nPr", npr, Arguments::Const(2)),
+        ("factorial", factorial, Arguments::Const(1)),
+        ("gamma", gamma, Arguments::Const(1)),
+        ("lgamma", lgamma, Arguments::Const(1)),
+        ("gcd", gcd, Arguments::Dynamic),
+        ("lcm", lcm, Arguments::Dynamic),
+        ("isprime", isprime, Arguments::Const(1)),
+        ("factor", factor, Arguments::Const(1)),
+    ];
+    for (name, func, args) in functions {
+        builtin::add_built_in_function(Function::new(name, func, args));
+    }
+}
+// the &Vec signatures are fixed by num_parser's Function type
+#[allow(clippy::vec_box)]
+type Call = fn(&Vec<Box<Expression>>, &Context, u32) -> EvalResult<Value>;
+
+// rand( becomes random( (see above) and a ! straight after a value is factorial, so 5! is factorial(5), but != is left alone
+pub fn preprocess(query: &str) -> String {
+    let mut out: Vec<char> = vec![];
+    let chars: Vec<char> = query.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        let start = match c {
+            '!' if chars.get(i + 1) != Some(&'=') => complex::left_operand(&out, out.len()),
+            _ => None,
+        };
+        match start {
+            Some(start) => {
+                let operand: String = out.drain(start..).collect();
+                out.extend(format!("factorial({})", operand.trim()).chars());
+            }
+            None => out.push(*c),
+        }
+        // the rand in a name like grand or rando doesn't count
+        let word_start = out.len() >= 5
+            && !out[..out.len() - 5]
+                .last()
+                .is_some_and(|c| c.is_alphanumeric());
+        if *c == '(' && word_start && out.ends_with(&['r', 'a', 'n', 'd', '(']) {
+            out.truncate(out.len() - 1);
+            out.extend("om(".chars());
+            // num_parser can't call a function with no arguments, so rand() is random(0,1)
+            if chars[i + 1..].iter().find(|c| **c != ' ') == Some(&')') {
+                out.extend("0,1".chars());
+            }
+        }
+    }
+    out.into_iter().collect()
+}
+
+// Declaring x = randint(1, 6) keeps the number it rolled, rather than rolling again every time x is looked at.
+// That goes for y = f(2) too when f rolls something, so a variable's value never changes by itself.
+// Function declarations are left alone so that f(x) = rand() is a new number each time
+pub fn freeze(name: &str, context: &mut Context, unit: Angle) {
+    let Some(body) = context.get_var(name) else {
+        return;
+    };
+    if !rolls(&body, context, &mut Vec::new()) {
+        return;
+    }
+    if let Ok(val) = num_parser::eval_with_static_context(name, &angle::context_in(context, unit)) {
+        context.add_variable(name.to_string(), Box::new(Expression::Literal(val)));
+    }
+}
+// seen keeps a function that calls itself from being looked into forever
+fn rolls(body: &Expression, context: &Context, seen: &mut Vec<String>) -> bool {
+    match body {
+        Expression::Func(name, args) => {
+            if matches!(name.as_str(), "random" | "randint") {
+                return true;
+            }
+            if args.iter().any(|a| rolls(a, context, seen)) {
+                return true;
+            }
+            match context.get_function(name) {
+                Some((_, inner)) if !seen.contains(name) => {
+                    seen.push(name.clone());
+                    rolls(&inner, context, seen)
+                }
+                _ => false,
+            }
+        }
+        Expression::Binary(a, _, b) => rolls(a, context, seen) || rolls(b, context, seen),
+        Expression::Unary(_, a) => rolls(a, context, seen),
+        Expression::Union(exprs) => exprs.iter().any(|e| rolls(e, context, seen)),
+        _ => false,
+    }
+}
+
+// SplitMix64, plenty for a calculator, started from the clock unless seed() was used
+fn next_random() -> u64 {
+    let mut state = STATE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let s = state.get_or_insert_with(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    *s = s.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *s;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+// from 0 up to but not including 1, with all 53 bits of an f64
+fn unit_random() -> f64 {
+    (next_random() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn random(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    // Dynamic rather than Const(2) so that the error names rand, which is what was typed
+    match arguments.len() {
+        2 => {
+            let a = arguments[0].eval(context, None, depth)?.as_float()?;
+            let b = arguments[1].eval(context, None, depth)?.as_float()?;
+            Ok(Value::Float(a + (b - a) * unit_random()))
+        }
+        given => Err(ErrorType::WrongFunctionArgumentsAmount {
+            func_name: "rand".to_string(),
+            expected: 2,
+            given: given as u8,
+        }),
+    }
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn randint(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let a = arguments[0].eval(context, None, depth)?.as_int()?;
+    let b = arguments[1].eval(context, None, depth)?.as_int()?;
+    if a > b {
+        return Err(during("randint", format!("{} is bigger than {}", a, b)));
+    }
+    // how many numbers there are to pick from, in u128 so that the full i64 range fits
+    let span = (b as i128 - a as i128 + 1) as u128;
+    // redrawing the few numbers at the very top keeps every result equally likely
+    let limit = u64::MAX as u128 + 1 - (u64::MAX as u128 + 1) % span;
+    let pick = loop {
+        let x = next_random() as u128;
+        if x < limit {
+            break x % span;
+        }
+    };
+    Ok(Value::Int((a as i128 + pick as i128) as i64))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn seed(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let n = arguments[0].eval(context, None, depth)?.as_int()?;
+    *STATE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(n as u64);
+    Ok(Value::Int(n))
+}
+
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn ncr(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (n, r) = counts("nCr", arguments, context, depth)?;
+    // nCr(n, r) = nCr(n, n - r), and the smaller one is fewer steps
+    let r = r.min(n - r);
+    // always divides exactly, since the running total is itself nCr(n - r + i, i),
+    // and it stops at the first overflow rather than carrying on for all r steps
+    let exact = (1..=r as u128).try_fold(1u128, |x, i| {
+        x.checked_mul((n - r) as u128 + i).map(|x| x / i)
+    });
+    whole("nCr", exact, || {
+        ln_factorial(n as f64) - ln_factorial(r as f64) - ln_factorial((n - r) as f64)
+    })
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn npr(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (n, r) = counts("nPr", arguments, context, depth)?;
+    let exact = (n - r + 1..=n).try_fold(1u128, |x, k| x.checked_mul(k as u128));
+    whole("nPr", exact, || {
+        ln_factorial(n as f64) - ln_factorial((n - r) as f64)
+    })
+}
+// whole numbers up to 20! are exact Ints, up to 34! (as far as u128 goes) the nearest float to the exact answer,
+// and bigger or fractional ones come from gamma(n + 1)
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn factorial(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let n = arguments[0].eval(context, None, depth)?.as_float()?;
+    if n < 0.0 && n.fract() == 0.0 {
+        return Err(during(
+            "factorial",
+            "there's no factorial of a negative whole number".to_string(),
+        ));
+    }
+    if n.fract() == 0.0 && n <= 34.0 {
+        return whole("factorial", Some((1..=n as u128).product()), || 0.0);
+    }
+    let x = gamma_of(n + 1.0);
+    match x.is_finite() {
+        true => Ok(Value::Float(x)),
+        false => Err(during(
+            "factorial",
+            format!(
+                "{}! is too big for a float, lgamma({} + 1) is its logarithm",
+                n, n
+            ),
+        )),
+    }
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn gamma(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let x = arguments[0].eval(context, None, depth)?.as_float()?;
+    if x <= 0.0 && x.fract() == 0.0 {
+        return Err(during("gamma", format!("gamma({}) is infinite", x)));
+    }
+    Ok(Value::Float(gamma_of(x)))
+}
+// the natural log of |gamma(x)|, which keeps going long after gamma itself overflows
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn lgamma(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let x = arguments[0].eval(context, None, depth)?.as_float()?;
+    if x <= 0.0 && x.fract() == 0.0 {
+        return Err(during("lgamma", format!("gamma({}) is infinite", x)));
+    }
+    Ok(Value::Float(ln_gamma(x)))
+}
+
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn gcd(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let numbers = integers("gcd", arguments, context, depth)?;
+    let g = numbers.iter().fold(0, |g, n| euclid(g, n.unsigned_abs()));
+    Ok(Value::Int(g as i64))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn lcm(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let numbers = integers("lcm", arguments, context, depth)?;
+    let mut l: u64 = 1;
+    for n in numbers.iter().map(|n| n.unsigned_abs()) {
+        if n == 0 {
+            return Ok(Value::Int(0));
+        }
+        l = (l / euclid(l, n))
+            .checked_mul(n)
+            .filter(|l| *l <= i64::MAX as u64)
+            .ok_or_else(|| during("lcm", "the answer is too big for an integer".to_string()))?;
+    }
+    Ok(Value::Int(l as i64))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn isprime(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let n = arguments[0].eval(context, None, depth)?.as_int()?;
+    Ok(Value::Bool(n > 1 && is_prime(n as u64)))
+}
+// negative numbers get a -1 at the front, factor(1) is an empty list
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn factor(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let n = arguments[0].eval(context, None, depth)?.as_int()?;
+    if n == 0 {
+        return Err(during("factor", "every number divides 0".to_string()));
+    }
+    let mut factors = vec![];
+    if n < 0 {
+        factors.push(Value::Int(-1));
+    }
+    let mut primes = prime_factors(n.unsigned_abs())?;
+    primes.sort();
+    factors.extend(primes.into_iter().map(|p| Value::Int(p as i64)));
+    Ok(Value::Vector(factors))
+}
+
+fn during(name: &'static str, message: String) -> ErrorType {
+    ErrorType::ErrorDuring {
+        operation_name: name,
+        error: Box::new(ErrorType::InternalError { message }),
+    }
+}
+// n and r for nCr and nPr, whole numbers with 0 <= r <= n
+fn counts(
+    name: &'static str,
+    arguments: &[Box<Expression>],
+    context: &Context,
+    depth: u32,
+) -> EvalResult<(u64, u64)> {
+    let n = arguments[0].eval(context, None, depth)?.as_int()?;
+    let r = arguments[1].eval(context, None, depth)?.as_int()?;
+    if n < 0 || r < 0 || r > n {
+        return Err(during(
+            name,
+            format!("needs 0 <= r <= n, got n = {} and r = {}", n, r),
+        ));
+    }
+    Ok((n as u64, r as u64))
+}
+// An exact count if it fits in an Int, the nearest float to it if it fits in a u128,
+// otherwise worked out from its logarithm, which is an error once even a float can't hold it
+fn whole(name: &'static str, exact: Option<u128>, ln: impl Fn() -> f64) -> EvalResult<Value> {
+    let x = match exact {
+        Some(x) if x <= i64::MAX as u128 => return Ok(Value::Int(x as i64)),
+        Some(x) => x as f64,
+        None => ln().exp(),
+    };
+    match x.is_finite() {
+        true => Ok(Value::Float(x)),
+        false => Err(during(
+            name,
+            "the answer is too big for a float, lgamma gives its logarithm".to_string(),
+        )),
+    }
+}
+// all the arguments evaluated and flattened, so gcd(list) works as well as gcd(12, 18)
+fn integers(
+    name: &'static str,
+    arguments: &[Box<Expression>],
+    context: &Context,
+    depth: u32,
+) -> EvalResult<Vec<i64>> {
+    let mut out = vec![];
+    for arg in arguments {
+        for value in arg.eval(context, None, depth)?.as_vector() {
+            out.push(value.as_int()?);
+        }
+    }
+    match out.is_empty() {
+        true => Err(during(name, "needs at least one integer".to_string())),
+        false => Ok(out),
+    }
+}
+fn euclid(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => euclid(b, a % b),
+    }
+}
+
+// Lanczos approximation, good to about 15 digits. Below 1/2 it goes through the reflection formula
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+fn gamma_of(x: f64) -> f64 {
+    if x < 0.5 {
+        return std::f64::consts::PI / ((std::f64::consts::PI * x).sin() * gamma_of(1.0 - x));
+    }
+    // whole numbers are exact while they still fit in an f64
+    if x.fract() == 0.0 && x <= 171.0 {
+        return (1..x as u64).fold(1.0, |f, k| f * k as f64);
+    }
+    ln_gamma(x).exp()
+}
+fn ln_gamma(x: f64) -> f64 {
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin().abs()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = LANCZOS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS[0], |s, (k, c)| s + c / (x + k as f64 + 1.0));
+    0.5 * std::f64::consts::TAU.ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+fn ln_factorial(n: f64) -> f64 {
+    ln_gamma(n + 1.0)
+}
+
+// Miller-Rabin with the bases that are known to be enough for every u64
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(p) = BASES.iter().find(|p| n.is_multiple_of(**p)) {
+        return n == *p;
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+    BASES.iter().all(|a| {
+        let mut x = pow_mod(*a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+// small factors by trial division, then Pollard's rho for whatever big ones are left, unless it's cancelled first
+fn prime_factors(mut n: u64) -> EvalResult<Vec<u64>> {
+    let mut factors = vec![];
+    for p in 2..1000 {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+    }
+    let mut left = vec![n];
+    while let Some(n) = left.pop() {
+        worker::check("factor")?;
+        if n == 1 {
+            continue;
+        }
+        if is_prime(n) {
+            factors.push(n);
+            continue;
+        }
+        let d = rho(n);
+        left.push(d);
+        left.push(n / d);
+    }
+    Ok(factors)
+}
+// some factor of n other than 1 or n, for n that's composite and has no factors under 1000
+fn rho(n: u64) -> u64 {
+    let mut c = 1;
+    loop {
+        let f = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = euclid(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+        c += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+
+    fn eval(query: &str) -> EvalResult<Value> {
+        testing::eval(&preprocess(query))
+    }
+
+    #[test]
+    fn preprocessing() {
+        assert_eq!(preprocess("5!"), "factorial(5)");
+        assert_eq!(preprocess("(n+1)! / 2"), "factorial((n+1)) / 2");
+        assert_eq!(preprocess("a != b"), "a != b");
+        assert_eq!(preprocess("rand()"), "random(0,1)");
+        assert_eq!(preprocess("rand(2, 5)"), "random(2, 5)");
+        assert_eq!(preprocess("grand(2) + rando"), "grand(2) + rando");
+    }
+
+    #[test]
+    fn choose_and_permute() {
+        assert_eq!(eval("nCr(5, 2)").unwrap(), Value::Int(10));
+        assert_eq!(eval("nCr(5, 0)").unwrap(), Value::Int(1));
+        assert_eq!(eval("nCr(5, 5)").unwrap(), Value::Int(1));
+        assert_eq!(eval("nPr(5, 2)").unwrap(), Value::Int(20));
+        assert_eq!(eval("nCr(62, 31)").unwrap(), Value::Int(465428353255261088));
+        assert!(eval("nCr(2, 3)").is_err());
+        assert!(eval("nPr(-1, 0)").is_err());
+    }
+
+    #[test]
+    fn counts_past_an_integer() {
+        // too big for i64 but exact in a u128, so the nearest float
+        let exact = 100891344545564193334812497256u128;
+        assert_eq!(eval("nCr(100, 50)").unwrap(), Value::Float(exact as f64));
+        // past u128, from the logarithm
+        let big = eval("nCr(1000, 500)").unwrap().as_float().unwrap();
+        assert!((big.log10() - 299.4).abs() < 0.1);
+        // and past what a float can hold
+        assert!(eval("nCr(2000, 1000)").is_err());
+        assert!(eval("nPr(200, 200)").is_err());
+    }
+
+    #[test]
+    fn factorials() {
+        assert_eq!(eval("0!").unwrap(), Value::Int(1));
+        assert_eq!(eval("20!").unwrap(), Value::Int(2432902008176640000));
+        // past i64 it's a float, the nearest one to the exact answer up to 34!
+        assert_eq!(
+            eval("25!").unwrap(),
+            Value::Float(15511210043330985984000000.0)
+        );
+        assert!((eval("0.5!").unwrap().as_float().unwrap() - 0.886226925452758).abs() < 1e-12);
+        assert!(eval("(-3)!").is_err());
+        assert!(eval("171!").is_err());
+        assert!(eval("lgamma(172)").is_ok());
+    }
+
+    #[test]
+    fn whole_number_functions() {
+        assert_eq!(eval("gcd(12, 18)").unwrap(), Value::Int(6));
+        assert_eq!(eval("lcm(4, 6, 10)").unwrap(), Value::Int(60));
+        assert_eq!(eval("isprime(97)").unwrap(), Value::Bool(true));
+        assert_eq!(eval("isprime(1)").unwrap(), Value::Bool(false));
+        let factors = |n: i64| eval(&format!("factor({})", n)).unwrap().as_vector();
+        let ints = |v: &[i64]| v.iter().map(|n| Value::Int(*n)).collect::<Vec<Value>>();
+        assert_eq!(factors(360), ints(&[2, 2, 2, 3, 3, 5]));
+        assert_eq!(factors(-12), ints(&[-1, 2, 2, 3]));
+        assert!(eval("factor(0)").is_err());
+    }
+
+    // everything random in one test, since the generator is shared and tests run at the same time
+    #[test]
+    fn random_numbers() {
+        let draw = || eval("seed(7) + randint(1, 1000000) * 0 + rand()").unwrap();
+        assert_eq!(draw(), draw());
+        for _ in 0..100 {
+            let x = eval("randint(1, 6)").unwrap().as_int().unwrap();
+            assert!((1..=6).contains(&x));
+        }
+        assert!(eval("randint(6, 1)").is_err());
+        // a variable keeps what it rolled, even through a function
+        let mut context = testing::context();
+        for decl in ["f(x) = randint(1, 1000000) + x", "y = f(0)"] {
+            num_parser::eval_with_mutable_context(&preprocess(decl), &mut context).unwrap();
+        }
+        freeze("y", &mut context, Angle::Radian);
+        assert!(matches!(
+            *context.get_var("y").unwrap(),
+            Expression::Literal(_)
+        ));
+        assert!(context.get_function("f").is_some());
+    }
+}
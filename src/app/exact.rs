@@ -0,0 +1,180 @@
+// exact rational arithmetic, so 1/3 stays 1/3 rather than becoming 0.33333
+// num_parser does everything in f64, so this evaluates the parsed query again through walker.rs using big rationals
+// anything that can't stay exact (pi, sqrt, sin, complex numbers, comparisons...) makes eval() give up and return None,
+// and the app just falls back to num_parser's float answer
+use crate::app::{
+    angle::{self, Angle},
+    format,
+    lib::DisplaySettings,
+    walker::{self, Engine},
+};
+use num::{BigInt, BigRational, Signed, ToPrimitive, Zero};
+use num_parser::{Context, Expression, Value};
+
+// 2^4096 is already over a thousand digits, past here it's not worth it
+const MAX_EXPONENT: i64 = 4096;
+
+pub fn eval(expr: &Expression, context: &Context) -> Option<BigRational> {
+    walker::eval(&Exact, expr, context)
+}
+
+struct Exact;
+impl Engine for Exact {
+    type Num = BigRational;
+    fn literal(&self, val: &Value) -> Option<BigRational> {
+        match val {
+            Value::Int(i) => Some(self.integer(*i)),
+            Value::Float(f) => decimal(*f),
+            _ => None,
+        }
+    }
+    fn constant(&self, _name: &str) -> Option<BigRational> {
+        // pi, e and friends are all irrational (or not numbers at all, like i)
+        None
+    }
+    fn integer(&self, i: i64) -> BigRational {
+        BigRational::from_integer(BigInt::from(i))
+    }
+    fn to_int(&self, n: &BigRational) -> Option<i64> {
+        if n.is_integer() {
+            n.to_integer().to_i64()
+        } else {
+            None
+        }
+    }
+    fn negate(&self, n: BigRational) -> Option<BigRational> {
+        Some(-n)
+    }
+    fn binary(&self, op: &str, a: BigRational, b: BigRational) -> Option<BigRational> {
+        match op {
+            "+" => Some(a + b),
+            "-" => Some(a - b),
+            "*" => Some(a * b),
+            "/" | "%" if b.is_zero() => None,
+            "/" => Some(a / b),
+            "%" => Some(a % b),
+            "^" => {
+                // only whole exponents stay rational, 4^(1/2) gets left to num_parser
+                let n = self.to_int(&b)?;
+                if n.abs() > MAX_EXPONENT || (a.is_zero() && n < 0) {
+                    return None;
+                }
+                Some(a.pow(n as i32))
+            }
+            _ => None,
+        }
+    }
+    fn function(&self, name: &str, args: &[BigRational], context: &Context) -> Option<BigRational> {
+        // deg(30) and the like stay exact between units that are a whole number to the turn, so not radians
+        if let (Some((_, from)), [x]) = (angle::UNIT_FUNCTIONS.iter().find(|f| f.0 == name), args) {
+            let to = Angle::of(context.angle_unit);
+            if *from == to {
+                return Some(x.clone());
+            }
+            let ratio = BigRational::new(to.per_turn()?.into(), from.per_turn()?.into());
+            return Some(x * ratio);
+        }
+        match (name, args) {
+            ("abs", [x]) => Some(x.abs()),
+            ("floor", [x]) => Some(x.floor()),
+            ("ceil", [x]) => Some(x.ceil()),
+            ("round", [x]) => Some(x.round()),
+            ("min", _) => args.iter().min().cloned(),
+            ("max", _) => args.iter().max().cloned(),
+            _ => None,
+        }
+    }
+}
+
+// Display on a float gives the shortest decimal that round trips, so 0.1 shows as "0.1" and becomes exactly 1/10
+pub fn decimal(f: f64) -> Option<BigRational> {
+    if !f.is_finite() {
+        return None;
+    }
+    let text = f.to_string();
+    let (whole, frac) = text.split_once('.').unwrap_or((&text, ""));
+    let numer: BigInt = format!("{}{}", whole, frac).parse().ok()?;
+    let denom = num::pow(BigInt::from(10), frac.len());
+    Some(BigRational::new(numer, denom))
+}
+
+pub fn fraction(r: &BigRational, mixed: bool) -> String {
+    // mixed numbers split off the whole part, 7/3 becomes 2 1/3
+    if mixed && !r.is_integer() && r.numer().abs() > *r.denom() {
+        let whole = r.trunc();
+        let rest = (r - &whole).abs();
+        format!("{} {}", whole.to_integer(), rest)
+    } else {
+        r.to_string()
+    }
+}
+
+// What the Output pane shows in exact mode, approx being the decimal answer (already localized)
+pub fn describe(r: &BigRational, approx: &str, display: &DisplaySettings) -> String {
+    if r.is_integer() {
+        // integers only need showing when num_parser couldn't keep them exact, e.g. 2^70
+        format::localize(&r.to_integer().to_string(), display.locale)
+    } else {
+        let exact = format::localize(&fraction(r, display.mixed), display.locale);
+        format!("{}\n≈ {}", exact, approx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{func, testing};
+
+    fn exact_in(query: &str, context: &Context) -> Option<String> {
+        eval(&func::parse(query, context)?, context).map(|r| r.to_string())
+    }
+    fn exact(query: &str) -> Option<String> {
+        exact_in(query, &testing::context())
+    }
+
+    #[test]
+    fn fractions_stay_exact() {
+        assert_eq!(exact("1/3+1/6"), Some("1/2".to_string()));
+        assert_eq!(exact("0.1+0.2"), Some("3/10".to_string()));
+        assert_eq!(exact("(2/3)^(-2)"), Some("9/4".to_string()));
+        assert_eq!(exact("7 % 3"), Some("1".to_string()));
+        assert_eq!(exact("2^70"), Some("1180591620717411303424".to_string()));
+        assert_eq!(exact("sum(1/k, k, 1, 4)"), Some("25/12".to_string()));
+        assert_eq!(exact("max(1/3, 1/4, 0.3)"), Some("1/3".to_string()));
+    }
+
+    #[test]
+    fn gives_up_on_anything_inexact() {
+        assert_eq!(exact("sqrt(2)"), None);
+        assert_eq!(exact("pi/2"), None);
+        assert_eq!(exact("4^(1/2)"), None);
+        assert_eq!(exact("1/0"), None);
+        assert_eq!(exact("0^(-1)"), None);
+        assert_eq!(exact("2^5000"), None);
+    }
+
+    #[test]
+    fn user_variables_and_functions() {
+        let mut context = testing::context();
+        for decl in ["a = 1/3", "f(x) = x/2 + a", "g(n) = g(n-1)"] {
+            num_parser::eval_with_mutable_context(decl, &mut context).unwrap();
+        }
+        assert_eq!(exact_in("f(1/3)", &context), Some("1/2".to_string()));
+        // runs into the depth limit rather than forever
+        assert_eq!(exact_in("g(1)", &context), None);
+    }
+
+    #[test]
+    fn display() {
+        let r = |n: i64, d: i64| BigRational::new(BigInt::from(n), BigInt::from(d));
+        assert_eq!(fraction(&r(7, 3), true), "2 1/3");
+        assert_eq!(fraction(&r(-7, 3), true), "-2 1/3");
+        assert_eq!(fraction(&r(7, 3), false), "7/3");
+        assert_eq!(fraction(&r(1, 3), true), "1/3");
+        assert_eq!(
+            describe(&r(1, 3), "0.33333333", &DisplaySettings::default()),
+            "1/3\n≈ 0.33333333"
+        );
+        assert_eq!(describe(&r(4, 2), "2", &DisplaySettings::default()), "2");
+    }
+}
@@ -0,0 +1,350 @@
+// money maths - time value of money, net present value, internal rate of return and compound interest
+// The TVM functions work the way spreadsheets do: rate is per period (0.05/12 for 5% a year paid monthly),
+// payments are at the end of each period, and money paid out is negative, so pmt(0.05/12, 360, 200000) is about -1073.64
+//   pv(rate, nper, pmt, [fv])    fv(rate, nper, pmt, [pv])    pmt(rate, nper, pv, [fv])
+//   nper(rate, pmt, pv, [fv])    rate(nper, pmt, pv, [fv])
+// npv(rate, flows) counts the first cash flow as now, undiscounted, and irr(flows) is the rate that makes npv zero.
+// The amortization schedule is the Options pane's table, see schedule()
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    Context, ErrorType, EvalResult, Expression, Value,
+};
+
+// Longer schedules than this (about 80 years of months) get refused, the table's for looking at
+pub const MAX_PERIODS: f64 = 1000.0;
+
+pub fn register() {
+    let functions: [(&'static str, Call); 8] = [
+        ("pv", pv),
+        ("fv", fv),
+        ("pmt", pmt),
+        ("nper", nper),
+        ("rate", rate),
+        ("npv", npv),
+        ("irr", irr),
+        ("compound", compound),
+    ];
+    for (name, func) in functions {
+        builtin::add_built_in_function(Function::new(name, func, Arguments::Dynamic));
+    }
+}
+// the &Vec signatures are fixed by num_parser's Function type
+#[allow(clippy::vec_box)]
+type Call = fn(&Vec<Box<Expression>>, &Context, u32) -> EvalResult<Value>;
+
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn pv(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let [r, n, pmt, fv] = tvm_args("pv", arguments, context, depth)?;
+    let growth = (1.0 + r).powf(n);
+    Ok(Value::Float(-(fv + pmt * annuity(r, n)) / growth))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn fv(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let [r, n, pmt, pv] = tvm_args("fv", arguments, context, depth)?;
+    Ok(Value::Float(future(r, n, pmt, pv)))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn pmt(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let [r, n, pv, fv] = tvm_args("pmt", arguments, context, depth)?;
+    Ok(Value::Float(payment(r, n, pv, fv)))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn nper(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let [r, pmt, pv, fv] = tvm_args("nper", arguments, context, depth)?;
+    let n = if r == 0.0 {
+        -(pv + fv) / pmt
+    } else {
+        ((pmt - fv * r) / (pmt + pv * r)).ln() / (1.0 + r).ln()
+    };
+    finite("nper", n)
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn rate(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let [n, pmt, pv, fv] = tvm_args("rate", arguments, context, depth)?;
+    let r = root(|r| future(r, n, pmt, pv) - fv, 0.01)
+        .ok_or_else(|| during("rate", "couldn't find a rate that works".to_string()))?;
+    finite("rate", r)
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn npv(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    if arguments.len() < 2 {
+        return Err(during(
+            "npv",
+            "needs a rate and some cash flows".to_string(),
+        ));
+    }
+    let r = arguments[0].eval(context, None, depth)?.as_float()?;
+    let flows = flatten(&arguments[1..], context, depth)?;
+    finite("npv", present(r, &flows))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn irr(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let flows = flatten(arguments, context, depth)?;
+    if !(flows.iter().any(|x| *x < 0.0) && flows.iter().any(|x| *x > 0.0)) {
+        return Err(during(
+            "irr",
+            "needs money going both in and out".to_string(),
+        ));
+    }
+    let r = root(|r| present(r, &flows), 0.1)
+        .ok_or_else(|| during("irr", "couldn't find a rate that works".to_string()))?;
+    finite("irr", r)
+}
+// compound(principal, annual rate, years, [times a year, 1 if left out])
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn compound(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let args = flatten(arguments, context, depth)?;
+    let (p, r, years, times) = match args[..] {
+        [p, r, years] => (p, r, years, 1.0),
+        [p, r, years, times] if times > 0.0 => (p, r, years, times),
+        _ => {
+            return Err(during(
+                "compound",
+                "takes principal, rate, years and how many times a year".to_string(),
+            ))
+        }
+    };
+    finite("compound", p * (1.0 + r / times).powf(times * years))
+}
+
+fn during(name: &'static str, message: String) -> ErrorType {
+    ErrorType::ErrorDuring {
+        operation_name: name,
+        error: Box::new(ErrorType::InternalError { message }),
+    }
+}
+fn finite(name: &'static str, x: f64) -> EvalResult<Value> {
+    match x.is_finite() {
+        true => Ok(Value::Float(x)),
+        false => Err(during(
+            name,
+            "there's no answer for those values".to_string(),
+        )),
+    }
+}
+fn flatten(arguments: &[Box<Expression>], context: &Context, depth: u32) -> EvalResult<Vec<f64>> {
+    let mut out = vec![];
+    for arg in arguments {
+        for value in arg.eval(context, None, depth)?.as_vector() {
+            out.push(value.as_float()?);
+        }
+    }
+    Ok(out)
+}
+// three arguments and an optional fourth that's 0 if left out
+fn tvm_args(
+    name: &'static str,
+    arguments: &[Box<Expression>],
+    context: &Context,
+    depth: u32,
+) -> EvalResult<[f64; 4]> {
+    match flatten(arguments, context, depth)?[..] {
+        [a, b, c] => Ok([a, b, c, 0.0]),
+        [a, b, c, d] => Ok([a, b, c, d]),
+        _ => Err(ErrorType::WrongFunctionArgumentsAmount {
+            func_name: name.to_string(),
+            expected: 4,
+            given: arguments.len() as u8,
+        }),
+    }
+}
+// what paying 1 a period for n periods adds up to
+fn annuity(r: f64, n: f64) -> f64 {
+    if r == 0.0 {
+        n
+    } else {
+        ((1.0 + r).powf(n) - 1.0) / r
+    }
+}
+fn future(r: f64, n: f64, pmt: f64, pv: f64) -> f64 {
+    -(pv * (1.0 + r).powf(n) + pmt * annuity(r, n))
+}
+fn payment(r: f64, n: f64, pv: f64, fv: f64) -> f64 {
+    -(pv * (1.0 + r).powf(n) + fv) / annuity(r, n)
+}
+fn present(r: f64, flows: &[f64]) -> f64 {
+    flows
+        .iter()
+        .enumerate()
+        .map(|(t, x)| x / (1.0 + r).powi(t as i32))
+        .sum()
+}
+// Secant method from a guess, good enough for the smooth curves here. None if it wanders off or never settles
+fn root(f: impl Fn(f64) -> f64, guess: f64) -> Option<f64> {
+    let (mut a, mut b) = (guess, guess * 1.1 + 0.001);
+    let (mut fa, mut fb) = (f(a), f(b));
+    for _ in 0..200 {
+        if fb.abs() < 1e-12 {
+            return Some(b);
+        }
+        if fb == fa {
+            break;
+        }
+        let next = b - fb * (b - a) / (fb - fa);
+        // rates under -100% don't mean anything
+        if !next.is_finite() || next <= -1.0 {
+            return None;
+        }
+        (a, fa) = (b, fb);
+        (b, fb) = (next, f(next));
+    }
+    (fb.abs() < 1e-7).then_some(b)
+}
+
+// One row of the amortization table
+#[derive(Debug)]
+pub struct Period {
+    pub number: u32,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub balance: f64,
+}
+// Paying off a loan of `principal` over `nper` equal payments at `rate` per period
+pub fn schedule(rate: f64, nper: f64, principal: f64) -> Result<Vec<Period>, String> {
+    if nper.fract() != 0.0 || !(1.0..=MAX_PERIODS).contains(&nper) {
+        return Err(format!(
+            "The number of payments has to be a whole number from 1 to {}",
+            MAX_PERIODS
+        ));
+    }
+    if rate <= -1.0 || !principal.is_finite() {
+        return Err("That rate or principal doesn't make sense".to_string());
+    }
+    let payment = -payment(rate, nper, principal, 0.0);
+    let mut balance = principal;
+    let mut periods = vec![];
+    for number in 1..=nper as u32 {
+        let interest = balance * rate;
+        balance -= payment - interest;
+        periods.push(Period {
+            number,
+            payment,
+            interest,
+            principal: payment - interest,
+            // the last few pennies of float error would show up as -0.00
+            balance: if number == nper as u32 { 0.0 } else { balance },
+        });
+    }
+    Ok(periods)
+}
+pub fn to_csv(periods: &[Period]) -> String {
+    let mut csv = "period,payment,interest,principal,balance\n".to_string();
+    for p in periods {
+        csv.push_str(&format!(
+            "{},{:.2},{:.2},{:.2},{:.2}\n",
+            p.number, p.payment, p.interest, p.principal, p.balance
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+
+    fn eval(query: &str) -> EvalResult<f64> {
+        testing::eval(query)?.as_float()
+    }
+    fn close(a: f64, b: f64, within: f64) -> bool {
+        (a - b).abs() < within
+    }
+
+    #[test]
+    fn time_value_of_money() {
+        assert!(close(
+            eval("pmt(0.05/12, 360, 200000)").unwrap(),
+            -1073.64,
+            0.005
+        ));
+        assert!(close(
+            eval("pv(0.05/12, 360, -1073.64)").unwrap(),
+            200000.0,
+            1.0
+        ));
+        assert!(close(
+            eval("nper(0.05/12, -1073.64, 200000)").unwrap(),
+            360.0,
+            0.01
+        ));
+        assert!(close(
+            eval("fv(0.05, 10, 0, -1000)").unwrap(),
+            1628.89,
+            0.005
+        ));
+        // no interest is just adding up
+        assert!(close(eval("pmt(0, 10, 1000)").unwrap(), -100.0, 1e-9));
+        assert!(close(eval("nper(0, -100, 1000)").unwrap(), 10.0, 1e-9));
+        assert!(eval("pmt(0.05, 10)").is_err());
+    }
+
+    #[test]
+    fn rates_are_found() {
+        assert!(close(
+            eval("rate(360, -1073.64, 200000)").unwrap() * 12.0,
+            0.05,
+            1e-5
+        ));
+        assert!(close(eval("irr(-100, 60, 60)").unwrap(), 0.1306623, 1e-6));
+        assert!(close(eval("npv(0.1, -100, 110)").unwrap(), 0.0, 1e-9));
+        assert!(close(
+            eval("compound(1000, 0.05, 10, 12)").unwrap(),
+            1647.01,
+            0.005
+        ));
+        assert!(eval("irr(100, 50)").is_err());
+        assert!(eval("npv(0.1)").is_err());
+        assert!(eval("compound(1000, 0.05, 10, 0)").is_err());
+    }
+
+    #[test]
+    fn root_finding() {
+        assert!(close(
+            root(|x| x * x - 2.0, 1.0).unwrap(),
+            2f64.sqrt(),
+            1e-9
+        ));
+        // wanders below -100%
+        assert_eq!(root(|x| x + 5.0, 0.1), None);
+        // never gets anywhere near 0
+        assert_eq!(root(|x| x * x + 1.0, 0.5), None);
+    }
+
+    #[test]
+    fn amortization() {
+        let periods = schedule(0.05 / 12.0, 360.0, 200000.0).unwrap();
+        assert_eq!(periods.len(), 360);
+        assert!(close(periods[0].payment, 1073.64, 0.005));
+        assert!(close(periods[0].interest, 833.33, 0.005));
+        assert!(close(periods[0].principal, 240.31, 0.005));
+        assert_eq!(periods[359].balance, 0.0);
+        // the principal paid off adds up to the loan
+        let paid: f64 = periods.iter().map(|p| p.principal).sum();
+        assert!(close(paid, 200000.0, 1e-6));
+        // no interest
+        assert!(close(
+            schedule(0.0, 4.0, 100.0).unwrap()[0].payment,
+            25.0,
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn schedules_that_dont_make_sense() {
+        assert!(schedule(0.01, 0.0, 100.0).is_err());
+        assert!(schedule(0.01, 2.5, 100.0).is_err());
+        assert!(schedule(0.01, MAX_PERIODS + 1.0, 100.0).is_err());
+        assert!(schedule(-1.0, 12.0, 100.0).is_err());
+        assert!(schedule(0.01, 12.0, f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn csv() {
+        let csv = to_csv(&schedule(0.0, 2.0, 100.0).unwrap());
+        assert_eq!(
+            csv,
+            "period,payment,interest,principal,balance\n1,50.00,0.00,50.00,50.00\n2,50.00,0.00,50.00,0.00\n"
+        );
+    }
+}
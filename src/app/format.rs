@@ -0,0 +1,446 @@
+// how numbers get written out - fixed decimal places, scientific, engineering or significant figures
+// Queries get evaluated without rounding and the format gets applied when things are displayed,
+// so changing it in Options changes the Output, History and User Variables panes all at once.
+// For scientific and engineering the rounding setting is the number of decimal places on the mantissa
+// On top of that the locale adds thousands separators and/or swaps the decimal point for a comma
+use crate::app::{complex, lib::DisplaySettings};
+use num_parser::{settings::Rounding, Context, Value};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat {
+    // num_parser's own rounding, 0.0000001 with 5 d.p. is just 0, except that anything too big for its digits
+    // to be exact is written in scientific instead
+    #[default]
+    Fixed,
+    // 1.23e-7
+    Scientific,
+    // exponents that are multiples of 3, 123e-9
+    Engineering,
+    // the same but with an SI prefix instead of the exponent, 123 n
+    Prefixed,
+    // N significant figures, trailing zeros included since they mean something
+    Significant(u32),
+}
+impl NumberFormat {
+    // What the Output pane title shows
+    pub fn name(&self) -> String {
+        match self {
+            NumberFormat::Fixed => "Fixed".to_string(),
+            NumberFormat::Scientific => "Sci".to_string(),
+            NumberFormat::Engineering => "Eng".to_string(),
+            NumberFormat::Prefixed => "Eng (SI)".to_string(),
+            NumberFormat::Significant(n) => format!("{} s.f.", n),
+        }
+    }
+    // Reads what was typed into the format select, anything unrecognised is Fixed
+    pub fn parse(text: &str) -> Self {
+        match text.trim().to_lowercase().as_str() {
+            "sci" | "s" => NumberFormat::Scientific,
+            "eng" | "e" => NumberFormat::Engineering,
+            "si" | "p" => NumberFormat::Prefixed,
+            other => match other.parse::<u32>() {
+                Ok(n @ 1..=17) => NumberFormat::Significant(n),
+                _ => NumberFormat::Fixed,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    // goes between each group of three digits before the decimal point, 1,234,567
+    pub group: Option<char>,
+    // 1,5 rather than 1.5
+    pub decimal_comma: bool,
+}
+impl Locale {
+    // Reads what was typed into the locale select, anything unrecognised is plain 1234.5
+    pub fn parse(text: &str) -> Self {
+        let (group, decimal_comma) = match text.trim().to_lowercase().as_str() {
+            "en" => (Some(','), false),
+            "eu" | "de" => (Some('.'), true),
+            "fr" => (Some(' '), true),
+            "si" => (Some(' '), false),
+            "ch" => (Some('\''), false),
+            "comma" => (None, true),
+            _ => (None, false),
+        };
+        Locale {
+            group,
+            decimal_comma,
+        }
+    }
+    // An example number in this locale, for the Output pane title
+    pub fn name(&self) -> String {
+        localize("1234567.89", *self)
+    }
+    // vectors are written [1, 2] normally, but with commas in the numbers that gets confusing
+    fn list_separator(&self) -> &str {
+        if self.decimal_comma || self.group == Some(',') {
+            "; "
+        } else {
+            ", "
+        }
+    }
+}
+
+// the biggest whole number every float below it can hold exactly
+const EXACT_LIMIT: f64 = 9007199254740992.0;
+
+const PREFIXES: [&str; 17] = [
+    "y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T", "P", "E", "Z", "Y",
+];
+
+pub fn value(val: &Value, context: &Context, display: &DisplaySettings) -> String {
+    localize(&plain(val, context, display), display.locale)
+}
+fn plain(val: &Value, context: &Context, display: &DisplaySettings) -> String {
+    let (rounding, format) = (context.rounding, display.format);
+    match (val, format) {
+        (Value::Float(x), _) => number(*x, rounding, format),
+        // integers stay exact unless a format that rewrites them is picked
+        (Value::Int(n), NumberFormat::Fixed) => n.to_string(),
+        (Value::Int(n), _) => number(*n as f64, rounding, format),
+        (Value::Complex(c), _) => complex::show(*c, display.complex, display.angle, |x| {
+            number(x, rounding, format)
+        }),
+        (Value::Vector(v), _) => format!(
+            "[{}]",
+            v.iter()
+                .map(|x| plain(x, context, display))
+                .collect::<Vec<String>>()
+                .join(display.locale.list_separator())
+        ),
+        (Value::Bool(b), _) => b.to_string(),
+    }
+}
+
+pub fn number(x: f64, rounding: Rounding, format: NumberFormat) -> String {
+    let decimals = match rounding {
+        Rounding::Round(n) => Some(n.min(16) as usize),
+        Rounding::NoRounding => None,
+    };
+    if !x.is_finite() {
+        return x.to_string();
+    }
+    match format {
+        // past 2^53 a float's digits stop being exact, so they're not written out as if they were
+        NumberFormat::Fixed if x.abs() >= EXACT_LIMIT => {
+            number(x, rounding, NumberFormat::Scientific)
+        }
+        NumberFormat::Fixed => Value::Float(x).round(rounding).to_string(),
+        NumberFormat::Scientific => {
+            let text = match decimals {
+                Some(d) => format!("{:.*e}", d, x),
+                None => format!("{:e}", x),
+            };
+            let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+            format!("{}e{}", trim_zeros(mantissa), exponent)
+        }
+        NumberFormat::Engineering | NumberFormat::Prefixed => {
+            if x == 0.0 {
+                return "0".to_string();
+            }
+            // dividing by 10^-9 and the like isn't exact, so the mantissa comes from shifting the digits of {:e} instead
+            let sci = format!("{:e}", x);
+            let (digits, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+            let exponent: i32 = exponent.parse().unwrap_or(0);
+            let mut mantissa: f64 = format!("{}e{}", digits, exponent.rem_euclid(3))
+                .parse()
+                .unwrap_or(x);
+            let mut exponent = exponent - exponent.rem_euclid(3);
+            let round = |m: f64| match decimals {
+                Some(d) => format!("{:.*}", d, m),
+                None => m.to_string(),
+            };
+            // 999.9999 can round up to 1000, which should really be 1 of the next prefix up
+            if round(mantissa.abs()).parse::<f64>().unwrap_or(0.0) >= 1000.0 {
+                exponent += 3;
+                mantissa /= 1000.0;
+            }
+            let mantissa = trim_zeros(&round(mantissa));
+            let prefix = PREFIXES.get(((exponent + 24) / 3) as usize);
+            match prefix {
+                Some(p) if format == NumberFormat::Prefixed && (-24..=24).contains(&exponent) => {
+                    format!("{} {}", mantissa, p).trim_end().to_string()
+                }
+                _ if exponent == 0 => mantissa,
+                _ => format!("{}e{}", mantissa, exponent),
+            }
+        }
+        NumberFormat::Significant(n) => {
+            if x == 0.0 {
+                return "0".to_string();
+            }
+            // rounding in scientific notation does the hard part
+            let text = format!("{:.*e}", n as usize - 1, x);
+            let (_, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+            let exponent: i32 = exponent.parse().unwrap_or(0);
+            if !(-5..16).contains(&exponent) {
+                return text;
+            }
+            let rounded: f64 = text.parse().unwrap_or(x);
+            let places = (n as i32 - 1 - exponent).max(0) as usize;
+            format!("{:.*}", places, rounded)
+        }
+    }
+}
+fn trim_zeros(text: &str) -> String {
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+// Adds the locale's separators to every number in some already formatted text, e.g. "1234.5 km" or "[1.5, 2]"
+// only the digits before the decimal point get grouped, and exponents are too short to ever be
+pub fn localize(text: &str, locale: Locale) -> String {
+    if locale == Locale::default() {
+        return text.to_string();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        // a number starts at a digit that isn't already part of one, or of a name like x2
+        let starts_number = chars[i].is_ascii_digit()
+            && (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '.'));
+        if !starts_number {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let whole = &chars[start..i];
+        for (n, digit) in whole.iter().enumerate() {
+            if n > 0 && (whole.len() - n).is_multiple_of(3) {
+                if let Some(group) = locale.group {
+                    out.push(group);
+                }
+            }
+            out.push(*digit);
+        }
+        if i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit() {
+            out.push(if locale.decimal_comma { ',' } else { '.' });
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+// The other way round, for input. With a decimal comma 1,5 is one and a half, so to keep commas working
+// between function arguments a comma only counts as a decimal point when it's the first one in a number
+// and has digits right up against it on both sides. max(1;5), max(1, 5) and max(x,5) are all still two arguments,
+// and (1,5,2,5) is the vector (1.5, 2.5). A ; always separates arguments
+pub fn delocalize(query: &str, locale: Locale) -> String {
+    if !locale.decimal_comma {
+        return query.to_string();
+    }
+    let chars: Vec<char> = query.chars().collect();
+    let mut out = String::new();
+    let mut in_number = false;
+    let mut has_point = false;
+    for (i, c) in chars.iter().enumerate() {
+        let digit_before = i > 0 && chars[i - 1].is_ascii_digit();
+        let digit_after = chars.get(i + 1).is_some_and(|c| c.is_ascii_digit());
+        match c {
+            ',' if in_number && !has_point && digit_before && digit_after => {
+                out.push('.');
+                has_point = true;
+                continue;
+            }
+            ';' => out.push(','),
+            c => out.push(*c),
+        }
+        if c.is_ascii_digit() {
+            if !in_number {
+                has_point = false;
+            }
+            in_number = true;
+        } else {
+            in_number = false;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed() {
+        assert_eq!(
+            number(1.0 / 3.0, Rounding::Round(3), NumberFormat::Fixed),
+            "0.333"
+        );
+        assert_eq!(number(2.5, Rounding::Round(3), NumberFormat::Fixed), "2.5");
+        // it's num_parser's rounding, so tiny values do round away, but digits past 2^53 aren't written out as if they were exact
+        assert_eq!(number(1.0e-7, Rounding::Round(5), NumberFormat::Fixed), "0");
+        let sin_pi = std::f64::consts::PI.sin();
+        assert_eq!(number(sin_pi, Rounding::Round(8), NumberFormat::Fixed), "0");
+        assert_eq!(
+            number(2.0f64.powi(60), Rounding::Round(2), NumberFormat::Fixed),
+            "1.15e18"
+        );
+        assert_eq!(
+            number(f64::INFINITY, Rounding::Round(2), NumberFormat::Fixed),
+            "inf"
+        );
+    }
+
+    #[test]
+    fn scientific() {
+        assert_eq!(
+            number(1.23e-7, Rounding::NoRounding, NumberFormat::Scientific),
+            "1.23e-7"
+        );
+        assert_eq!(
+            number(123456.0, Rounding::Round(2), NumberFormat::Scientific),
+            "1.23e5"
+        );
+        assert_eq!(
+            number(100.0, Rounding::Round(2), NumberFormat::Scientific),
+            "1e2"
+        );
+    }
+
+    #[test]
+    fn engineering() {
+        assert_eq!(
+            number(1.23e-7, Rounding::Round(3), NumberFormat::Engineering),
+            "123e-9"
+        );
+        assert_eq!(
+            number(12345.0, Rounding::Round(3), NumberFormat::Engineering),
+            "12.345e3"
+        );
+        assert_eq!(
+            number(5.0, Rounding::Round(3), NumberFormat::Engineering),
+            "5"
+        );
+        // rounding up to 1000 moves on to the next exponent
+        assert_eq!(
+            number(999.9999, Rounding::Round(2), NumberFormat::Engineering),
+            "1e3"
+        );
+    }
+
+    #[test]
+    fn prefixed() {
+        assert_eq!(
+            number(1.23e-7, Rounding::Round(3), NumberFormat::Prefixed),
+            "123 n"
+        );
+        assert_eq!(
+            number(4700.0, Rounding::Round(3), NumberFormat::Prefixed),
+            "4.7 k"
+        );
+        assert_eq!(number(5.0, Rounding::Round(3), NumberFormat::Prefixed), "5");
+        // past yotta there's no prefix left
+        assert_eq!(
+            number(1.0e27, Rounding::Round(3), NumberFormat::Prefixed),
+            "1e27"
+        );
+    }
+
+    #[test]
+    fn significant_figures() {
+        let sig = |x, n| number(x, Rounding::NoRounding, NumberFormat::Significant(n));
+        assert_eq!(sig(1.23456, 3), "1.23");
+        // trailing zeros mean something here
+        assert_eq!(sig(2.0, 3), "2.00");
+        assert_eq!(sig(123456.0, 2), "120000");
+        assert_eq!(sig(1.5e-9, 2), "1.5e-9");
+    }
+
+    #[test]
+    fn parsing_the_options() {
+        assert_eq!(NumberFormat::parse(" SCI "), NumberFormat::Scientific);
+        assert_eq!(NumberFormat::parse("4"), NumberFormat::Significant(4));
+        assert_eq!(NumberFormat::parse("18"), NumberFormat::Fixed);
+        assert_eq!(NumberFormat::parse("nonsense"), NumberFormat::Fixed);
+    }
+
+    #[test]
+    fn values_inside_vectors_and_complex_numbers() {
+        let context = Context {
+            rounding: Rounding::Round(2),
+            ..Context::default()
+        };
+        let sci = DisplaySettings {
+            format: NumberFormat::Scientific,
+            ..DisplaySettings::default()
+        };
+        let v = Value::Vector(vec![Value::Int(1200), Value::Float(0.5)]);
+        assert_eq!(value(&v, &context, &sci), "[1.2e3, 5e-1]");
+        // integers are left alone in Fixed
+        assert_eq!(
+            value(
+                &Value::Int(123456789),
+                &context,
+                &DisplaySettings::default()
+            ),
+            "123456789"
+        );
+        let z = Value::Complex(num::complex::Complex64::new(1500.0, -1.0));
+        assert_eq!(value(&z, &context, &sci), "1.5e3-1e0i");
+    }
+
+    #[test]
+    fn values_in_a_locale() {
+        let eu = DisplaySettings {
+            locale: Locale::parse("eu"),
+            ..DisplaySettings::default()
+        };
+        let v = Value::Vector(vec![Value::Float(1234.5), Value::Int(2)]);
+        // the list separator changes too, so the commas aren't ambiguous
+        assert_eq!(value(&v, &Context::default(), &eu), "[1.234,5; 2]");
+    }
+
+    #[test]
+    fn localize_groups_digits() {
+        let en = Locale::parse("en");
+        assert_eq!(localize("1234567.89", en), "1,234,567.89");
+        assert_eq!(localize("1234.5 km", en), "1,234.5 km");
+        assert_eq!(localize("-1234", en), "-1,234");
+        // decimals, exponents and names with digits in stay as they are
+        assert_eq!(localize("0.12345", en), "0.12345");
+        assert_eq!(localize("1.5e-123", en), "1.5e-123");
+        assert_eq!(localize("x1234", en), "x1234");
+        assert_eq!(localize("123", en), "123");
+    }
+
+    #[test]
+    fn localize_every_locale() {
+        let example = |name| Locale::parse(name).name();
+        assert_eq!(example("en"), "1,234,567.89");
+        assert_eq!(example("eu"), "1.234.567,89");
+        assert_eq!(example("fr"), "1 234 567,89");
+        assert_eq!(example("si"), "1 234 567.89");
+        assert_eq!(example("ch"), "1'234'567.89");
+        assert_eq!(example("comma"), "1234567,89");
+        assert_eq!(example("anything else"), "1234567.89");
+    }
+
+    #[test]
+    fn delocalize_decimal_commas() {
+        let eu = Locale::parse("eu");
+        assert_eq!(delocalize("1,5 + 2", eu), "1.5 + 2");
+        assert_eq!(delocalize("(1,5,2,5)", eu), "(1.5,2.5)");
+        // still two arguments
+        assert_eq!(delocalize("max(1;5)", eu), "max(1,5)");
+        assert_eq!(delocalize("max(1, 5)", eu), "max(1, 5)");
+        assert_eq!(delocalize("max(x,5)", eu), "max(x,5)");
+        assert_eq!(delocalize("max(1,5; 2)", eu), "max(1.5, 2)");
+        // a point doesn't change
+        assert_eq!(delocalize("1,5", Locale::parse("en")), "1,5");
+    }
+}
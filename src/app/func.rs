@@ -3,25 +3,68 @@
 // funcs/vars_to_strings takes a list of similar expressions and their corresponding names and spits out the strings you see in the left-hand panels
 // vars_to_strings will also find float approximations for each expression, when possible
 // when not possible (e.g. variable x = y+1 defined in terms of unknown y) it returns the error
+// in precision mode the approximations are worked out to the chosen number of decimal places instead
+// constants from the library have their own pane, so they're left out here while it's turned on
+// lists (see stats.rs) show the whole list with a count and mean, and matrices their size and rows
+use crate::app::{angle, constants, format, lib::DisplaySettings, matrix, precise, stats};
 use num_parser::{Expression::*, *};
-pub fn vars_to_strings(context: &Context) -> Vec<String> {
+pub fn vars_to_strings(context: &Context, display: &DisplaySettings) -> Vec<String> {
     // More complex than the functions one
     // Same principle but we also want to evaluate e.g. sin(10) as well as having the closed form
     // and if the closed form is the same as the evaluation (e.g. variable is an integer), then don't display it
     let name_bodies = context
         .variables
         .iter()
-        .map(|(name, body)| (name, parser(&body)));
+        .filter(|(name, _)| !(display.constants && constants::find(name).is_some()))
+        .map(|(name, body)| (name, parser(body)));
+    // evaluated unrounded, since the number format does the rounding, and in gradians converted (see angle.rs)
+    let mut raw = angle::context_in(context, display.angle);
+    raw.rounding = settings::Rounding::NoRounding;
     let mut out = vec![];
     for (name, body) in name_bodies {
-        match num_parser::eval_with_static_context(&*body, context) {
+        // lists get written as [..] which only means something after stats::preprocess
+        let working = angle::converted(&stats::preprocess(&body), display.angle);
+        match num_parser::eval_with_static_context(&working, &raw) {
             Ok(val) => {
-                if let Value::Int(_) = val {
+                let precise = display
+                    .precision
+                    .and_then(|digits| precise::eval(&*raw.get_var(name)?, &raw, digits));
+                if let Some(long) = precise.filter(|long| *long != body) {
+                    let long = format::localize(&long, display.locale);
+                    out.push(format!("\n{} = {} = {}", name, body, long))
+                } else if let Some(grid) =
+                    matrix::compact(&val, |x| format::value(x, context, display))
+                {
+                    out.push(format!("\n{} = {}", name, grid))
+                } else if let Value::Vector(list) = &val {
+                    // lists get the whole list and a summary rather than their list(...) body
+                    out.push(format!(
+                        "\n{} = {}",
+                        name,
+                        format::value(&val, context, display)
+                    ));
+                    let numbers: Option<Vec<f64>> =
+                        list.iter().map(|v| v.as_float().ok()).collect();
+                    // only for data sets typed in as a list, the mean of linreg's [m, c] means nothing
+                    let typed = body.starts_with('[');
+                    if let Some(numbers) = numbers.filter(|n| typed && !n.is_empty()) {
+                        let mean = Value::Float(stats::average(&numbers));
+                        out.push(format!(
+                            "\n   n = {}, mean = {}",
+                            numbers.len(),
+                            format::value(&mean, context, display)
+                        ))
+                    }
+                } else if let Value::Int(n) = val {
                     // No need to give float approximation if answer is exact integer
-                    // and "x = 2 = 2" looks silly
-                    out.push(format!("\n{} = {}", name, body))
+                    // and "x = 2 = 2" looks silly, but x = nCr(5,2) = 10 still needs its value
+                    match n.to_string() == body {
+                        true => out.push(format!("\n{} = {}", name, body)),
+                        false => out.push(format!("\n{} = {} = {}", name, body, n)),
+                    }
                 } else {
-                    out.push(format!("\n{} = {} = {}", name, body, val))
+                    let shown = format::value(&val, context, display);
+                    out.push(format!("\n{} = {} = {}", name, body, shown))
                 }
             }
             Err(msg) => out.push(format!("\n{} = {} ({})", name, body, msg)),
@@ -35,35 +78,113 @@ pub fn funcs_to_strings(context: &Context) -> Vec<String> {
     context
         .functions
         .iter()
-        .map(|(name, (vars, body))| format!("\n{}({}) = {}", name, vars.join(","), parser(&body)))
+        .map(|(name, (vars, body))| format!("\n{}({}) = {}", name, vars.join(","), parser(body)))
         .collect::<Vec<String>>()
 }
+pub fn parse(query: &str, context: &Context) -> Option<Expression> {
+    // num_parser has no public way to just parse a query into an Expression
+    // but declaring it as a variable stores the parsed tree, so do that on a throwaway copy of the context and take it back out
+    // the name doesn't matter, a query using z itself still refers to the real z since the body is only stored, not evaluated
+    let mut scratch = context.clone();
+    num_parser::eval_with_mutable_context(&format!("z={}", query), &mut scratch).ok()?;
+    scratch.variables.remove("z").map(|body| *body)
+}
+pub fn assignment(query: &str) -> Option<usize> {
+    // where the = of a declaration like m = 2 or f(x) = x^2 is, counted in chars
+    // comparisons like == and <= don't count
+    let chars: Vec<char> = query.chars().collect();
+    (0..chars.len()).find(|&i| {
+        chars[i] == '='
+            && !matches!(chars.get(i + 1), Some('='))
+            && !(i > 0 && matches!(chars[i - 1], '<' | '>' | '!' | '='))
+    })
+}
+pub fn new_name(query: &str, context: &Context) -> Option<String> {
+    // the name in a declaration like data = [1,2] if num_parser wouldn't recognise it yet.
+    // It splits names it doesn't know into letters, d*a*t*a = [1,2] isn't a declaration at all
+    let chars: Vec<char> = query.chars().collect();
+    let name: String = chars[..assignment(query)?].iter().collect();
+    let name = name.trim();
+    let known = context.variables.contains_key(name)
+        || function::builtin::reserved_keywords().contains(&name);
+    (name.chars().count() > 1 && name.chars().all(char::is_alphabetic) && !known)
+        .then(|| name.to_string())
+}
 pub fn parser(body: &Expression) -> String {
     // I'm relying on num_parser to be well written to avoid recursion issues
     // No problems yet and I've done a bit of testing
     // But there's no depth limit etc.
+    // Brackets are only added where precedence() says they're needed, e.g. f(x) = (x-3)^4
     match body {
         Binary(expr1, token, expr2) => {
-            format!("{}{}{}", parser(&*expr1), token, parser(&*expr2))
+            let prec = precedence(body);
+            // num_parser reads x^2^3 as (x^2)^3, so that one gets brackets on the left too
+            let left_assoc = token.to_string() == "^";
+            format!(
+                "{}{}{}",
+                bracketed(expr1, precedence(expr1) < prec + left_assoc as u8),
+                token,
+                bracketed(expr2, precedence(expr2) <= prec)
+            )
         }
-        Unary(token, expr) => format!("{}{}", token, parser(&*expr)),
+        Unary(token, expr) => format!(
+            "{}{}",
+            token,
+            bracketed(expr, precedence(expr) <= precedence(body))
+        ),
         Var(s) => s.clone(),
         Func(name, exprs) => {
+            // list(1,2) is how [1,2] gets stored, so write it back the way it was typed
+            let (open, close) = match name.as_str() {
+                "list" => ("[".to_string(), "]"),
+                _ => (format!("{}(", name), ")"),
+            };
             format!(
-                "{}({})",
-                name,
+                "{}{}{}",
+                open,
                 exprs
                     .iter()
-                    .map(|e| parser(&*e))
+                    .map(|e| bracketed(e, matches!(**e, Union(_))))
                     .collect::<Vec<String>>()
-                    .join(",")
+                    .join(","),
+                close
             )
         }
         Literal(val) => val.to_string(),
         Union(exprs) => exprs
             .iter()
-            .map(|e| parser(&*e))
+            .map(|e| bracketed(e, matches!(**e, Union(_))))
             .collect::<Vec<String>>()
             .join(","),
     }
 }
+fn bracketed(body: &Expression, needs_brackets: bool) -> String {
+    if needs_brackets {
+        format!("({})", parser(body))
+    } else {
+        parser(body)
+    }
+}
+fn precedence(body: &Expression) -> u8 {
+    // same numbers num_parser uses when building its trees
+    // unary minus binds looser than ^, so -2^2 is -(2^2) but (-2)^2 needs its brackets
+    match body {
+        Binary(_, token, _) => match &*token.to_string() {
+            "^" => 90,
+            "*" | "/" | "%" => 70,
+            "+" | "-" => 60,
+            "<" | ">" | "<=" | ">=" => 50,
+            "==" | "!=" => 40,
+            "&&" => 30,
+            "||" => 20,
+            _ => 0,
+        },
+        Unary(token, _) if token.to_string() == "!" => 80,
+        Unary(_, _) => 60,
+        Union(_) => 10,
+        Literal(Value::Int(i)) if *i < 0 => 60,
+        Literal(Value::Float(f)) if *f < 0.0 => 60,
+        Literal(Value::Complex(_)) => 60,
+        _ => 255,
+    }
+}
@@ -0,0 +1,590 @@
+// where each pane goes on the screen, worked out the same way for drawing and for working out what a mouse click hit,
+// and the settings behind it from Options > Pane layout
+use crate::app::config;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    text::Text,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use std::collections::BTreeMap;
+
+// how much one press of a resize key moves things, in percent
+const STEP: u16 = 5;
+// Narrower than this, e.g. a tmux split, and the three columns are too thin to read, so everything goes in one column
+// with tabs for the list panes. Shorter than MINIMAL_HEIGHT and there's only room for the input and the answer on one line
+const COMPACT_WIDTH: u16 = 80;
+const MINIMAL_HEIGHT: u16 = 10;
+// the compact layout's tabs, and the function key that picks each one
+const TABS: [(Pane, &str, u8); 4] = [
+    (Pane::Vars, "Variables", 1),
+    (Pane::Funcs, "Functions", 2),
+    (Pane::Constants, "Constants", 3),
+    (Pane::History, "History", 4),
+];
+
+// The sizes and which panes are shown, changed from Options and kept in the config file between runs.
+// Widths are percentages of the screen, the left column's heights are shares of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutSettings {
+    pub left: u16,
+    pub right: u16,
+    pub vars: u16,
+    pub funcs: u16,
+    pub constants: u16,
+    // percent of the middle column's height, the input gets the rest
+    pub output: u16,
+    pub show_vars: bool,
+    pub show_funcs: bool,
+    pub show_history: bool,
+}
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        LayoutSettings {
+            left: 25,
+            right: 25,
+            vars: 35,
+            funcs: 30,
+            constants: 35,
+            output: 60,
+            show_vars: true,
+            show_funcs: true,
+            show_history: true,
+        }
+    }
+}
+
+// the pane the layout keys act on, picked with <Up>/<Down> in Options > Pane layout
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    #[default]
+    Vars,
+    Funcs,
+    Constants,
+    Output,
+    Input,
+    History,
+}
+impl Pane {
+    pub fn name(&self) -> &str {
+        match self {
+            Pane::Vars => "User Variables",
+            Pane::Funcs => "User Functions",
+            Pane::Constants => "Constants",
+            Pane::Output => "Output",
+            Pane::Input => "Input",
+            Pane::History => "History",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Panes {
+    // any of these can be hidden, and Constants only shows while the library is turned on
+    pub vars: Option<Rect>,
+    pub funcs: Option<Rect>,
+    pub constants: Option<Rect>,
+    pub history: Option<Rect>,
+    // the tab bar, only in the compact layout
+    pub tabs: Option<Rect>,
+    pub output: Rect,
+    // the input, or the Options pane and its pickers
+    pub input: Rect,
+    // the whole middle column, which the amortization table takes over
+    pub middle: Rect,
+}
+impl Panes {
+    // the ones on screen, in the order <Down> goes through them
+    pub fn shown(&self) -> Vec<(Pane, Rect)> {
+        let all = [
+            (Pane::Vars, self.vars),
+            (Pane::Funcs, self.funcs),
+            (Pane::Constants, self.constants),
+            (Pane::Output, Some(self.output)),
+            (Pane::Input, Some(self.input)),
+            (Pane::History, self.history),
+        ];
+        all.into_iter()
+            .filter_map(|(pane, rect)| Some((pane, rect?)))
+            .collect()
+    }
+}
+
+pub fn minimal(area: Rect) -> bool {
+    area.height < MINIMAL_HEIGHT
+}
+// picking is for Options and the pickers, which need the room more than the tabs do
+pub fn panes(
+    area: Rect,
+    settings: &LayoutSettings,
+    constants: bool,
+    tab: Pane,
+    picking: bool,
+) -> Panes {
+    if minimal(area) {
+        // the one line only needs the input, anything else (Options...) gets the whole screen
+        return Panes {
+            output: Rect { height: 0, ..area },
+            input: area,
+            middle: area,
+            ..Panes::default()
+        };
+    }
+    if area.width < COMPACT_WIDTH {
+        return compact(area, settings, constants, tab, picking);
+    }
+    let left_panes: Vec<(Pane, u16)> = [
+        (Pane::Vars, settings.vars, settings.show_vars),
+        (Pane::Funcs, settings.funcs, settings.show_funcs),
+        (Pane::Constants, settings.constants, constants),
+    ]
+    .into_iter()
+    .filter(|(_, _, shown)| *shown)
+    .map(|(pane, share, _)| (pane, share))
+    .collect();
+
+    // a column with nothing in it gives its room to the middle
+    let left_width = if left_panes.is_empty() {
+        0
+    } else {
+        settings.left
+    };
+    let right_width = if settings.show_history {
+        settings.right
+    } else {
+        0
+    };
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![
+            Constraint::Percentage(left_width),
+            Constraint::Percentage(100 - left_width - right_width),
+            Constraint::Percentage(right_width),
+        ])
+        .split(area);
+
+    let total: u16 = left_panes.iter().map(|(_, share)| share).sum();
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            left_panes
+                .iter()
+                .map(|(_, share)| Constraint::Ratio(*share as u32, total as u32)),
+        )
+        .split(columns[0]);
+    let find = |pane: Pane| {
+        let i = left_panes.iter().position(|(p, _)| *p == pane)?;
+        Some(left[i])
+    };
+
+    let middle = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Percentage(settings.output),
+            Constraint::Percentage(100 - settings.output),
+        ])
+        .split(columns[1]);
+
+    Panes {
+        vars: find(Pane::Vars),
+        funcs: find(Pane::Funcs),
+        constants: find(Pane::Constants),
+        history: settings.show_history.then_some(columns[2]),
+        tabs: None,
+        output: middle[0],
+        input: middle[1],
+        middle: columns[1],
+    }
+}
+
+// One column: the tab bar, whichever list pane its tab is on, then Output and Input.
+// The sizes from Options > Pane layout are for the columns so they don't come into it, but hidden panes don't get a tab
+fn compact(
+    area: Rect,
+    settings: &LayoutSettings,
+    constants: bool,
+    tab: Pane,
+    picking: bool,
+) -> Panes {
+    let tab = current_tab(settings, constants, tab).filter(|_| !picking);
+    let constraints = match (tab, picking) {
+        (Some(_), _) => vec![
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(5),
+            Constraint::Percentage(35),
+        ],
+        (None, true) => vec![
+            Constraint::Length(0),
+            Constraint::Length(0),
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ],
+        (None, false) => vec![
+            Constraint::Length(0),
+            Constraint::Length(0),
+            Constraint::Percentage(60),
+            Constraint::Percentage(40),
+        ],
+    };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
+    let list = |pane: Pane| (tab == Some(pane)).then_some(rows[1]);
+    Panes {
+        vars: list(Pane::Vars),
+        funcs: list(Pane::Funcs),
+        constants: list(Pane::Constants),
+        history: list(Pane::History),
+        tabs: tab.map(|_| rows[0]),
+        output: rows[2],
+        input: rows[3],
+        middle: rows[2].union(rows[3]),
+    }
+}
+// the list panes that get a tab, the ones that aren't hidden
+pub fn tabs(settings: &LayoutSettings, constants: bool) -> Vec<Pane> {
+    let shown = [
+        settings.show_vars,
+        settings.show_funcs,
+        constants,
+        settings.show_history,
+    ];
+    TABS.iter()
+        .zip(shown)
+        .filter(|(_, shown)| *shown)
+        .map(|((pane, _, _), _)| *pane)
+        .collect()
+}
+// the tab that's showing, the first one if the one picked has since been hidden
+pub fn current_tab(settings: &LayoutSettings, constants: bool, tab: Pane) -> Option<Pane> {
+    let tabs = tabs(settings, constants);
+    tabs.iter().find(|t| **t == tab).or(tabs.first()).copied()
+}
+// e.g. " Variables <F1> "
+pub fn tab_label(pane: Pane) -> String {
+    match TABS.iter().find(|(p, _, _)| *p == pane) {
+        Some((_, name, key)) => format!(" {} <F{}> ", name, key),
+        None => String::new(),
+    }
+}
+pub fn tab_for_key(key: u8) -> Option<Pane> {
+    TABS.iter()
+        .find(|(_, _, k)| *k == key)
+        .map(|(pane, _, _)| *pane)
+}
+// which tab is at a column of the tab bar, they're drawn one after the other from the left
+pub fn tab_at(bar: Rect, tabs: &[Pane], col: u16) -> Option<Pane> {
+    let mut left = bar.x;
+    for tab in tabs {
+        let width = tab_label(*tab).chars().count() as u16;
+        if (left..left + width).contains(&col) {
+            return Some(*tab);
+        }
+        left += width;
+    }
+    None
+}
+
+impl LayoutSettings {
+    // taller or shorter, within its column. History has the whole column to itself so there's nothing to do
+    pub fn taller(&mut self, pane: Pane, grow: bool) {
+        let share = match pane {
+            Pane::Vars => &mut self.vars,
+            Pane::Funcs => &mut self.funcs,
+            Pane::Constants => &mut self.constants,
+            // the input's height is whatever the output leaves, and the output keeps at least 20% like the config does
+            Pane::Output | Pane::Input => {
+                self.output = step(self.output, grow == (pane == Pane::Output), 20, 80);
+                return;
+            }
+            Pane::History => return,
+        };
+        *share = step(*share, grow, 10, 80);
+    }
+    // wider or narrower, the middle column always keeps at least 30%
+    pub fn wider(&mut self, pane: Pane, grow: bool) {
+        match pane {
+            Pane::Vars | Pane::Funcs | Pane::Constants => {
+                self.left = step(self.left, grow, 10, (70 - self.right).min(45))
+            }
+            Pane::History => self.right = step(self.right, grow, 10, (70 - self.left).min(45)),
+            // the middle takes from, or gives back to, whichever side needs it more
+            Pane::Output | Pane::Input => match (grow, self.left >= self.right) {
+                (true, true) | (false, false) => self.left = step(self.left, !grow, 10, 45),
+                (true, false) | (false, true) => self.right = step(self.right, !grow, 10, 45),
+            },
+        }
+        // a narrower side might have left too little, put it back within bounds
+        self.right = self.right.min(70 - self.left);
+    }
+    pub fn toggle(&mut self, pane: Pane) {
+        match pane {
+            Pane::Vars => self.show_vars = !self.show_vars,
+            Pane::Funcs => self.show_funcs = !self.show_funcs,
+            Pane::History => self.show_history = !self.show_history,
+            _ => (),
+        }
+    }
+    pub fn load() -> Self {
+        // anything missing or not a number just stays as it is by default
+        let values = config::load();
+        let default = LayoutSettings::default();
+        let number = |key: &str, or: u16, min: u16, max: u16| {
+            values
+                .get(key)
+                .and_then(|v| v.parse::<u16>().ok())
+                .map_or(or, |n| n.clamp(min, max))
+        };
+        let flag = |key: &str, or: bool| values.get(key).and_then(|v| v.parse().ok()).unwrap_or(or);
+        let left = number("left_width", default.left, 10, 45);
+        LayoutSettings {
+            left,
+            right: number("right_width", default.right, 10, 45).min(70 - left),
+            vars: number("variables_height", default.vars, 10, 80),
+            funcs: number("functions_height", default.funcs, 10, 80),
+            constants: number("constants_height", default.constants, 10, 80),
+            output: number("output_height", default.output, 20, 80),
+            show_vars: flag("show_variables", default.show_vars),
+            show_funcs: flag("show_functions", default.show_funcs),
+            show_history: flag("show_history", default.show_history),
+        }
+    }
+    pub fn save(&self) -> std::io::Result<()> {
+        let values = BTreeMap::from([
+            ("left_width", self.left.to_string()),
+            ("right_width", self.right.to_string()),
+            ("variables_height", self.vars.to_string()),
+            ("functions_height", self.funcs.to_string()),
+            ("constants_height", self.constants.to_string()),
+            ("output_height", self.output.to_string()),
+            ("show_variables", self.show_vars.to_string()),
+            ("show_functions", self.show_funcs.to_string()),
+            ("show_history", self.show_history.to_string()),
+        ]);
+        config::save(values)
+    }
+}
+fn step(n: u16, grow: bool, min: u16, max: u16) -> u16 {
+    match grow {
+        true => (n + STEP).min(max),
+        false => n.saturating_sub(STEP).max(min),
+    }
+}
+
+// inside a pane's border
+pub fn inner(pane: Rect) -> Rect {
+    Block::default().borders(Borders::ALL).inner(pane)
+}
+// how many rows some text takes up once it's wrapped to fit a width, same as the panes wrap it
+pub fn rows(text: &str, width: u16) -> usize {
+    Paragraph::new(Text::from(text))
+        .wrap(Wrap { trim: false })
+        .line_count(width)
+}
+// The list panes (Variables, History...) are a run of entries that each start with a newline,
+// so there's an empty first row and then each entry's rows. Which entry is on row `row` of the text, if any
+pub fn entry_at(entries: &[String], width: u16, row: usize) -> Option<usize> {
+    let mut top = 1;
+    for (i, entry) in entries.iter().enumerate() {
+        let height = rows(entry.trim_start_matches('\n'), width);
+        if (top..top + height).contains(&row) {
+            return Some(i);
+        }
+        top += height;
+    }
+    None
+}
+// the furthest the text in a pane can scroll, so that its last row is at the bottom
+pub fn max_scroll(text: &str, pane: Rect) -> u16 {
+    let inner = inner(pane);
+    let total = rows(text, inner.width);
+    total.saturating_sub(inner.height as usize) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panes_tile_the_screen() {
+        let area = Rect::new(0, 0, 100, 40);
+        let panes = panes(area, &LayoutSettings::default(), true, Pane::Vars, false);
+        assert_eq!(panes.vars, Some(Rect::new(0, 0, 25, 14)));
+        assert_eq!(panes.funcs, Some(Rect::new(0, 14, 25, 12)));
+        assert_eq!(panes.constants, Some(Rect::new(0, 26, 25, 14)));
+        assert_eq!(panes.output, Rect::new(25, 0, 50, 24));
+        assert_eq!(panes.input, Rect::new(25, 24, 50, 16));
+        assert_eq!(panes.history, Some(Rect::new(75, 0, 25, 40)));
+        assert_eq!(panes.middle, panes.output.union(panes.input));
+        assert_eq!(panes.shown().len(), 6);
+        // without the constants the other two share the left column
+        let panes = super::panes(area, &LayoutSettings::default(), false, Pane::Vars, false);
+        assert_eq!(panes.constants, None);
+        assert_eq!(panes.vars.unwrap().height + panes.funcs.unwrap().height, 40);
+    }
+
+    #[test]
+    fn hidden_panes_give_their_room_away() {
+        let area = Rect::new(0, 0, 100, 40);
+        let settings = LayoutSettings {
+            show_vars: false,
+            show_funcs: false,
+            show_history: false,
+            ..LayoutSettings::default()
+        };
+        let panes = panes(area, &settings, false, Pane::Vars, false);
+        assert_eq!(panes.middle, area);
+        assert_eq!(
+            panes.shown().iter().map(|(p, _)| *p).collect::<Vec<_>>(),
+            [Pane::Output, Pane::Input]
+        );
+        // the constants on their own have the whole left column
+        let panes = super::panes(area, &settings, true, Pane::Vars, false);
+        assert_eq!(panes.constants, Some(Rect::new(0, 0, 25, 40)));
+    }
+
+    #[test]
+    fn resizing_stays_in_bounds() {
+        let mut settings = LayoutSettings::default();
+        for _ in 0..20 {
+            settings.taller(Pane::Vars, true);
+            settings.taller(Pane::Input, true);
+            settings.wider(Pane::Funcs, true);
+        }
+        assert_eq!(settings.vars, 80);
+        assert_eq!(settings.output, 20);
+        assert_eq!(settings.left, 45);
+        // the middle keeps its 30%
+        for _ in 0..20 {
+            settings.wider(Pane::History, true);
+        }
+        assert_eq!(settings.right, 25);
+        settings.taller(Pane::Input, false);
+        assert_eq!(settings.output, 25);
+        // shrinking the output is the same as growing the input
+        for _ in 0..20 {
+            settings.taller(Pane::Output, false);
+        }
+        assert_eq!(settings.output, 20);
+        settings.taller(Pane::Output, true);
+        assert_eq!(settings.output, 25);
+        // the middle growing takes from the wider side first
+        settings.wider(Pane::Output, true);
+        assert_eq!((settings.left, settings.right), (40, 25));
+        for _ in 0..20 {
+            settings.wider(Pane::Input, true);
+        }
+        assert_eq!((settings.left, settings.right), (10, 10));
+        for _ in 0..20 {
+            settings.wider(Pane::Output, false);
+        }
+        assert!(settings.left + settings.right <= 70);
+        // nothing to do for history's height
+        let before = settings;
+        settings.taller(Pane::History, true);
+        assert_eq!(settings, before);
+    }
+
+    #[test]
+    fn toggling() {
+        let mut settings = LayoutSettings::default();
+        settings.toggle(Pane::Funcs);
+        assert!(!settings.show_funcs);
+        settings.toggle(Pane::Funcs);
+        assert!(settings.show_funcs);
+        // the output and input are always there
+        settings.toggle(Pane::Output);
+        assert_eq!(settings, LayoutSettings::default());
+    }
+
+    #[test]
+    fn wrapping_and_scrolling() {
+        assert_eq!(inner(Rect::new(0, 0, 10, 5)), Rect::new(1, 1, 8, 3));
+        assert_eq!(rows("abcdefghij", 4), 3);
+        assert_eq!(rows("ab\ncd", 10), 2);
+        // 1 + 7 rows of text in a pane with room for 3
+        let text = "\nabcdefghij\nab\nabcdefghijkl";
+        assert_eq!(max_scroll(text, Rect::new(0, 0, 6, 5)), 5);
+        assert_eq!(max_scroll("\nab", Rect::new(0, 0, 6, 5)), 0);
+    }
+
+    #[test]
+    fn entries_by_row() {
+        let entries = ["\nabcdefghij".to_string(), "\nab".to_string()];
+        // row 0 is the empty line the first newline makes
+        assert_eq!(entry_at(&entries, 4, 0), None);
+        assert_eq!(entry_at(&entries, 4, 1), Some(0));
+        assert_eq!(entry_at(&entries, 4, 3), Some(0));
+        assert_eq!(entry_at(&entries, 4, 4), Some(1));
+        assert_eq!(entry_at(&entries, 4, 5), None);
+    }
+
+    #[test]
+    fn compact_is_one_column_with_tabs() {
+        let area = Rect::new(0, 0, 60, 30);
+        let settings = LayoutSettings::default();
+        let panes = panes(area, &settings, true, Pane::History, false);
+        assert_eq!(panes.tabs, Some(Rect::new(0, 0, 60, 1)));
+        // only the list pane the tab's on
+        assert_eq!(
+            (panes.vars, panes.funcs, panes.constants),
+            (None, None, None)
+        );
+        let history = panes.history.unwrap();
+        assert_eq!((history.y, history.width), (1, 60));
+        assert_eq!(history.bottom(), panes.output.y);
+        assert_eq!(panes.output.height, 5);
+        assert_eq!(panes.input.bottom(), 30);
+        // Options and the pickers take the tabs' room
+        let panes = super::panes(area, &settings, true, Pane::History, true);
+        assert_eq!((panes.tabs, panes.history), (None, None));
+        assert_eq!(panes.input.height, 25);
+    }
+
+    #[test]
+    fn minimal_is_just_the_input() {
+        let area = Rect::new(0, 0, 120, 6);
+        assert!(minimal(area));
+        let panes = panes(area, &LayoutSettings::default(), true, Pane::Vars, false);
+        assert_eq!(panes.input, area);
+        assert_eq!(panes.output.height, 0);
+        assert!(panes
+            .shown()
+            .iter()
+            .all(|(p, _)| matches!(p, Pane::Output | Pane::Input)));
+    }
+
+    #[test]
+    fn tabs_follow_what_is_shown() {
+        let mut settings = LayoutSettings::default();
+        assert_eq!(tabs(&settings, true).len(), 4);
+        settings.toggle(Pane::Vars);
+        assert_eq!(tabs(&settings, false), [Pane::Funcs, Pane::History]);
+        // a hidden tab falls back to the first one there is
+        assert_eq!(current_tab(&settings, false, Pane::Vars), Some(Pane::Funcs));
+        assert_eq!(
+            current_tab(&settings, false, Pane::History),
+            Some(Pane::History)
+        );
+        settings.toggle(Pane::Funcs);
+        settings.toggle(Pane::History);
+        assert_eq!(current_tab(&settings, false, Pane::Vars), None);
+        assert_eq!(tab_for_key(3), Some(Pane::Constants));
+        assert_eq!(tab_for_key(5), None);
+    }
+
+    #[test]
+    fn tab_at_a_column() {
+        let bar = Rect::new(2, 0, 60, 1);
+        let tabs = [Pane::Vars, Pane::History];
+        // " Variables <F1> " is 16 wide, " History <F4> " 14
+        assert_eq!(tab_label(Pane::Vars), " Variables <F1> ");
+        assert_eq!(tab_at(bar, &tabs, 1), None);
+        assert_eq!(tab_at(bar, &tabs, 2), Some(Pane::Vars));
+        assert_eq!(tab_at(bar, &tabs, 17), Some(Pane::Vars));
+        assert_eq!(tab_at(bar, &tabs, 18), Some(Pane::History));
+        assert_eq!(tab_at(bar, &tabs, 31), Some(Pane::History));
+        assert_eq!(tab_at(bar, &tabs, 32), None);
+    }
+}
@@ -1,19 +1,36 @@
 // my structs and enums
-use num_parser::{self, settings};
+use crate::app::{
+    angle::Angle,
+    complex::ComplexFormat,
+    constants,
+    format::{self, Locale, NumberFormat},
+    func, precise,
+};
+use num_parser::{self, settings, Value};
 // App is instantiated using Default, so using a wrapper around our num_parser::Context allows us to define a custom default to be instantiated
 // However it's annoying to keep typing self.ctxt.0 to access the actual Context
 // So we impl Deref, DerefMut. Now &self.ctxt gives a reference to the inner context etc.
 use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
+
+// as deep as recursion can go and still fit on the stack main.rs gives the worker. Each level of depth is a nested
+// expression rather than a whole function call, a simple recursive function takes three, and the worker tests
+// run one right up to the limit. Choosing max in Options means this one, so nothing can run off the end of the stack
+// and take the terminal with it
+pub const MAX_DEPTH: u32 = 2000;
 
 #[derive(Debug)]
 pub struct ContextWrapper<T>(T);
 impl Default for ContextWrapper<num_parser::Context> {
     fn default() -> Self {
-        ContextWrapper(num_parser::Context::new(
+        let mut context = num_parser::Context::new(
             settings::Rounding::Round(5),
             settings::AngleUnit::Radian,
             settings::DepthLimit::Limit(100),
-        ))
+        );
+        // the constants library starts off turned on
+        constants::load(&mut context);
+        ContextWrapper(context)
     }
 }
 impl<T> Deref for ContextWrapper<T> {
@@ -28,12 +45,117 @@ impl<T> DerefMut for ContextWrapper<T> {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum AppMode {
     #[default]
     Normal,
     Option,
-    RoundingSelect,
+    // typing in a value for one of the Options settings
+    Select(Setting),
+    AmortizationView,
+    LayoutSelect,
+}
+
+// The Options settings that are picked by typing something in and hitting <Enter>. They all work the same way,
+// so each one only needs its title and what to tell the user here, and what to do with the text in App::apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Rounding,
+    Angle,
+    Depth,
+    TimeLimit,
+    Amortization,
+    Simplify,
+    Programmer,
+    Precision,
+    Format,
+    Locale,
+}
+impl Setting {
+    pub fn title(&self) -> &str {
+        match self {
+            Setting::Rounding => "Rounding",
+            Setting::Angle => "Angle Unit",
+            Setting::Depth => "Recursion Depth",
+            Setting::TimeLimit => "Time Limit",
+            Setting::Amortization => "Amortization Table",
+            Setting::Simplify => "Simplify",
+            Setting::Programmer => "Programmer Mode",
+            Setting::Precision => "Arbitrary Precision",
+            Setting::Format => "Number Format",
+            Setting::Locale => "Number Locale",
+        }
+    }
+    pub fn prompt(&self, display: &DisplaySettings) -> String {
+        match self {
+            Setting::Rounding => "Enter an integer between 0 and 16 and hit <Enter> \nAny non-integer input will lead to no rounding".to_string(),
+            Setting::Angle => "Enter rad, deg, grad or turn and hit <Enter>\nA single value can always be given its own unit, e.g. sin(30°) or cos(1.2rad)".to_string(),
+            Setting::Depth => format!("Enter how deep recursive functions can go, up to {}, and hit <Enter>. The current limit is in the Output title\nOr max for {}, as deep as the calculator can go", MAX_DEPTH, MAX_DEPTH),
+            Setting::TimeLimit => {
+                let current = match display.time_limit {
+                    Some(n) => format!("{} seconds", n),
+                    None => "none".to_string(),
+                };
+                format!("Enter how many seconds a query can take before it's given up on and hit <Enter>, currently {}\nOr none to wait as long as it takes, <Esc> still cancels", current)
+            }
+            Setting::Amortization => "Enter the rate per period, the number of payments and the amount borrowed, and hit <Enter>\ne.g. 0.05/12, 30*12, 200000 for 30 years of monthly payments at 5% a year".to_string(),
+            Setting::Simplify => "Enter the name of a variable or function and hit <Enter> \nIts definition will be replaced with a simplified version".to_string(),
+            Setting::Programmer => "Enter a word size of 8, 16, 32 or 64 and hit <Enter>, e.g. 32 or u32 for unsigned\nAnything else turns programmer mode off".to_string(),
+            Setting::Precision => format!("Enter a number of decimal places up to {} and hit <Enter>, e.g. 50 \nAny non-integer input turns arbitrary precision off", precise::MAX_DIGITS),
+            Setting::Format => "Enter sci for scientific, eng for engineering, si for engineering with SI prefixes\nor a number of significant figures, and hit <Enter>\nAnything else goes back to fixed decimal places".to_string(),
+            Setting::Locale => "Enter en (1,234.5), eu (1.234,5), fr (1 234,5), si (1 234.5), ch (1'234.5) or comma (1234,5) and hit <Enter>\nWith a decimal comma, separate function arguments with ; or a space, e.g. max(1,5; 2)\nAnything else goes back to plain 1234.5".to_string(),
+        }
+    }
+}
+
+// Display settings that don't belong in the num_parser Context, set from the Options pane
+#[derive(Debug)]
+pub struct DisplaySettings {
+    // programmer mode shows integer results in dec/hex/oct/bin, wrapped to a word of word_size bits
+    pub programmer: bool,
+    pub word_size: u32,
+    pub signed: bool,
+    // exact mode keeps rationals as fractions, mixed shows them as e.g. 2 1/3 rather than 7/3
+    pub exact: bool,
+    pub mixed: bool,
+    // Some(n) evaluates to n decimal places with big numbers instead of f64, for when 16 isn't enough
+    pub precision: Option<u32>,
+    // whether the constants library is loaded into the context, see constants.rs
+    pub constants: bool,
+    // fixed/scientific/engineering/significant figures, see format.rs
+    pub format: NumberFormat,
+    // thousands separators and decimal commas, also see format.rs
+    pub locale: Locale,
+    // a+bi, r∠θ or r·e^(θi)
+    pub complex: ComplexFormat,
+    // the context only knows num_parser's units, this can also be gradians
+    pub angle: Angle,
+    // how many seconds a query gets before it's given up on, None waits forever, see worker.rs
+    pub time_limit: Option<u64>,
+}
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            programmer: false,
+            word_size: 64,
+            signed: true,
+            exact: false,
+            mixed: false,
+            precision: None,
+            constants: true,
+            format: NumberFormat::Fixed,
+            locale: Locale::default(),
+            complex: ComplexFormat::Rectangular,
+            angle: Angle::Radian,
+            time_limit: Some(30),
+        }
+    }
+}
+impl DisplaySettings {
+    pub fn word_name(&self) -> String {
+        // i32, u8 etc.
+        format!("{}{}", if self.signed { "i" } else { "u" }, self.word_size)
+    }
 }
 
 // Scroll directions - used to send messages to the scroller about how it'll change state. Could be a boolean, this is maybe clearer.
@@ -59,14 +181,19 @@ impl Input {
     pub fn insert(&mut self, c: char) {
         self.before.push(c);
     }
+    pub fn insert_str(&mut self, s: &str) {
+        // pasting, goes in at the cursor all at once
+        self.before.push_str(s);
+    }
     pub fn shift(&mut self, dir: CursorDir) {
         match dir {
-            CursorDir::Left => match self.before.pop() {
-                Some(c) => self.after.insert(0, c),
-                None => (),
-            },
+            CursorDir::Left => {
+                if let Some(c) = self.before.pop() {
+                    self.after.insert(0, c)
+                }
+            }
             CursorDir::Right => {
-                if self.after.len() > 0 {
+                if !self.after.is_empty() {
                     self.before.push(self.after.remove(0))
                 }
             }
@@ -91,6 +218,81 @@ impl Input {
         self.after = String::new();
         self.before = new_str;
     }
+    pub fn set_cursor(&mut self, pos: usize) {
+        // clicking in the input, pos counts characters from the start and goes no further than the end
+        let text = self.get_text();
+        let split = text.char_indices().nth(pos).map_or(text.len(), |(i, _)| i);
+        self.before = text[..split].to_string();
+        self.after = text[split..].to_string();
+    }
+}
+// How far the list panes have been scrolled with the mouse wheel, in rows
+#[derive(Debug, Default)]
+pub struct PaneScroll {
+    pub vars: u16,
+    pub funcs: u16,
+    pub constants: u16,
+    pub history: u16,
+}
+// A message that only shows for a few seconds, in place of the Input pane's title, e.g. after saving a file
+// The app ticks while there is one so it goes away on time, see App::handle_events
+#[derive(Debug)]
+pub struct Flash {
+    pub text: String,
+    until: Instant,
+}
+impl Flash {
+    pub fn new(text: String) -> Self {
+        Flash {
+            text,
+            until: Instant::now() + Duration::from_secs(3),
+        }
+    }
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.until
+    }
+}
+// The Variables pane's lines from func::vars_to_strings, which works out every variable, so they're only redone
+// after something that could change them: a query finishing, Options changing a setting, or clearing everything.
+// Nothing else can, since a variable never depends on the clock (now and today only exist in date queries)
+// and one that rolls a random number keeps what it rolled, see discrete::freeze
+#[derive(Debug, Default)]
+pub struct VarsCache {
+    fresh: bool,
+    lines: Vec<String>,
+}
+impl VarsCache {
+    pub fn refresh(&mut self, context: &num_parser::Context, display: &DisplaySettings) {
+        if !self.fresh {
+            self.lines = func::vars_to_strings(context, display);
+            self.fresh = true;
+        }
+    }
+    pub fn invalidate(&mut self) {
+        self.fresh = false;
+    }
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+}
+
+// History keeps values as they came out of num_parser so they can be formatted however the current settings say
+// Results that only exist as text (e.g. from units.rs) just get shown as they are, with the locale's digit grouping,
+// except for Verbatim ones like dates where grouping would get in the way
+#[derive(Debug, Clone)]
+pub enum Answer {
+    Value(Value),
+    Text(String),
+    Verbatim(String),
+}
+impl Answer {
+    pub fn show(&self, context: &num_parser::Context, display: &DisplaySettings) -> String {
+        match self {
+            Answer::Value(val) => format::value(val, context, display),
+            Answer::Text(text) => format::localize(text, display.locale),
+            Answer::Verbatim(text) => text.clone(),
+        }
+    }
 }
 pub enum HistoryEntry {
     Query(usize),
@@ -98,16 +300,15 @@ pub enum HistoryEntry {
 }
 #[derive(Debug, Default)]
 pub struct Queries {
-    contents: Vec<(String, String)>,
+    contents: Vec<(String, Answer)>,
     pos: usize,
     temp: Option<String>,
 }
 impl Queries {
     pub fn try_store(&mut self, s: String) {
-        match self.temp {
-            None => self.temp = Some(s),
-            Some(_) => (),
-        };
+        if self.temp.is_none() {
+            self.temp = Some(s)
+        }
     }
     pub fn try_restore(&mut self) -> Option<String> {
         std::mem::take(&mut self.temp)
@@ -121,6 +322,10 @@ impl Queries {
         };
     }
 
+    pub fn select(&mut self, n: usize) {
+        // jumping straight to the nth newest entry, from a click, as if Up had been pressed n+1 times
+        self.pos = std::cmp::min(n + 1, self.contents.len());
+    }
     pub fn scroll_reset(&mut self) {
         self.pos = 0;
     }
@@ -129,29 +334,88 @@ impl Queries {
         // This internal call uses zero to represent a non-scrolling state, so n-1 gives the last element of the history
         match self.pos {
             0 => None,
-            n => Some(self.retrieve(HistoryEntry::Query(n - 1)).clone()),
+            n => Some(self.retrieve(HistoryEntry::Query(n - 1))),
         }
     }
+    #[allow(clippy::wrong_self_convention)]
     fn from_end(&self, index: usize) -> usize {
-        std::cmp::max((self.contents.len() - index - 1) as i32, 0) as usize
+        // saturating, the subtraction used to underflow (and panic) for an index past the oldest entry
+        self.contents.len().saturating_sub(index + 1)
     }
-    pub fn retrieve(&self, entry: HistoryEntry) -> &String {
+    pub fn retrieve(&self, entry: HistoryEntry) -> String {
+        // values come back as num_parser would display them
         match entry {
-            HistoryEntry::Query(n) => &self.contents[self.from_end(n)].0,
-            HistoryEntry::Value(n) => &self.contents[self.from_end(n)].1,
+            HistoryEntry::Query(n) => self.contents[self.from_end(n)].0.clone(),
+            HistoryEntry::Value(n) => match &self.contents[self.from_end(n)].1 {
+                Answer::Value(val) => val.to_string(),
+                Answer::Text(text) | Answer::Verbatim(text) => text.clone(),
+            },
         }
     }
-    pub fn archive(&mut self, input: String, output: String) {
+    pub fn archive(&mut self, input: String, output: Answer) {
         self.contents.push((input, output));
     }
-    pub fn render_all(&self) -> Vec<String> {
+    pub fn render_all(
+        &self,
+        context: &num_parser::Context,
+        display: &DisplaySettings,
+    ) -> Vec<String> {
         self.contents
             .iter()
             .rev()
-            .map(|(a, b)| format!("\n {} = {}", a, b))
+            .map(|(a, b)| format!("\n {} = {}", a, b.show(context, display)))
             .collect()
     }
     pub fn get_pos(&self) -> usize {
         self.pos
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vars_cache_is_redone_when_told_to() {
+        let mut context = crate::app::testing::context();
+        let mut display = DisplaySettings {
+            constants: false,
+            ..DisplaySettings::default()
+        };
+        let mut cache = VarsCache::default();
+        cache.refresh(&context, &display);
+        assert!(cache.lines().is_empty());
+        // nothing changes until it's invalidated, that's the point
+        num_parser::eval_with_mutable_context("x = 1/4", &mut context).unwrap();
+        cache.refresh(&context, &display);
+        assert!(cache.lines().is_empty());
+        cache.invalidate();
+        cache.refresh(&context, &display);
+        assert_eq!(cache.lines(), ["\nx = 1/4 = 0.25"]);
+        display.format = NumberFormat::Scientific;
+        cache.invalidate();
+        cache.refresh(&context, &display);
+        assert_eq!(cache.lines(), ["\nx = 1/4 = 2.5e-1"]);
+    }
+
+    #[test]
+    fn flash_messages_run_out() {
+        assert!(!Flash::new("Saved".to_string()).expired());
+        let old = Flash {
+            text: "Saved".to_string(),
+            until: Instant::now(),
+        };
+        assert!(old.expired());
+    }
+
+    #[test]
+    fn history_past_the_oldest_entry() {
+        let mut history = Queries::default();
+        history.archive("1+1".to_string(), Answer::Value(Value::Int(2)));
+        history.archive("2+2".to_string(), Answer::Value(Value::Int(4)));
+        assert_eq!(history.retrieve(HistoryEntry::Query(0)), "2+2");
+        assert_eq!(history.retrieve(HistoryEntry::Value(1)), "2");
+        // used to underflow, now it's just the oldest
+        assert_eq!(history.retrieve(HistoryEntry::Query(5)), "1+1");
+    }
+}
@@ -0,0 +1,569 @@
+// matrices and vectors, built on the lists from stats.rs - a matrix is a list of rows, [[1,2],[3,4]]
+// num_parser already does + - and scaling on lists element by element, so this adds the rest as built-ins:
+// transpose, det, inv, matmul(a, b), solve(a, b) for ax = b, dot, cross, identity(n), and at(m, i, j)
+// which m[i,j] gets rewritten to (see stats::preprocess). Indices start at 1
+// Only real numbers, everything gets worked out in f64
+// num_parser's * and ^ work element by element too, which for a matrix is hardly ever what was meant,
+// so those are errors pointing at matmul instead, see check_query
+use crate::app::func;
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    Context, ErrorType, EvalResult, Expression, Value,
+};
+
+type Matrix = Vec<Vec<f64>>;
+
+// how small a pivot can be, relative to the biggest entry, before the matrix counts as singular
+const PIVOT_TOLERANCE: f64 = 1e-12;
+
+pub fn register() {
+    let functions: [(&'static str, Call, Arguments); 9] = [
+        ("transpose", transpose, Arguments::Const(1)),
+        ("det", det, Arguments::Const(1)),
+        ("inv", inv, Arguments::Const(1)),
+        ("matmul", matmul, Arguments::Const(2)),
+        ("solve", solve, Arguments::Const(2)),
+        ("dot", dot, Arguments::Const(2)),
+        ("cross", cross, Arguments::Const(2)),
+        ("identity", identity, Arguments::Const(1)),
+        ("at", at, Arguments::Dynamic),
+    ];
+    for (name, func, args) in functions {
+        builtin::add_built_in_function(Function::new(name, func, args));
+    }
+}
+// the &Vec signatures are fixed by num_parser's Function type
+#[allow(clippy::vec_box)]
+type Call = fn(&Vec<Box<Expression>>, &Context, u32) -> EvalResult<Value>;
+
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn transpose(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let m = matrix("transpose", arguments[0].eval(context, None, depth)?, false)?;
+    Ok(from_matrix(flip(&m)))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn det(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let m = square("det", arguments[0].eval(context, None, depth)?)?;
+    Ok(Value::Float(eliminate(m, vec![]).0))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn inv(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let m = square("inv", arguments[0].eval(context, None, depth)?)?;
+    let n = m.len();
+    let (determinant, inverse) = eliminate(m, unit(n));
+    if determinant == 0.0 {
+        return Err(during("inv", "the matrix is singular".to_string()));
+    }
+    Ok(from_matrix(inverse))
+}
+// a plain list counts as a row on the left and a column on the right, so matmul(m, v) gives back a list
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn matmul(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let left = arguments[0].eval(context, None, depth)?;
+    let right = arguments[1].eval(context, None, depth)?;
+    let column = !is_matrix(&right);
+    let a = matrix("matmul", left, false)?;
+    let b = matrix("matmul", right, true)?;
+    if a[0].len() != b.len() {
+        return Err(during(
+            "matmul",
+            format!(
+                "a {}×{} can't multiply a {}×{}",
+                a.len(),
+                a[0].len(),
+                b.len(),
+                b[0].len()
+            ),
+        ));
+    }
+    let product: Matrix = a
+        .iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|j| row.iter().zip(&b).map(|(x, b_row)| x * b_row[j]).sum())
+                .collect()
+        })
+        .collect();
+    Ok(match column {
+        true => from_list(product.into_iter().map(|row| row[0]).collect()),
+        false => from_matrix(product),
+    })
+}
+// solve(a, b) is x in ax = b, for a list b the answer's a list too
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn solve(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let a = square("solve", arguments[0].eval(context, None, depth)?)?;
+    let right = arguments[1].eval(context, None, depth)?;
+    let column = !is_matrix(&right);
+    let b = matrix("solve", right, true)?;
+    if b.len() != a.len() {
+        return Err(during(
+            "solve",
+            format!("needs {} values on the right, got {}", a.len(), b.len()),
+        ));
+    }
+    let (determinant, x) = eliminate(a, b);
+    if determinant == 0.0 {
+        return Err(during("solve", "the matrix is singular".to_string()));
+    }
+    Ok(match column {
+        true => from_list(x.into_iter().map(|row| row[0]).collect()),
+        false => from_matrix(x),
+    })
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn dot(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let u = list("dot", arguments[0].eval(context, None, depth)?)?;
+    let v = list("dot", arguments[1].eval(context, None, depth)?)?;
+    if u.len() != v.len() {
+        return Err(during(
+            "dot",
+            format!("lengths {} and {} don't match", u.len(), v.len()),
+        ));
+    }
+    Ok(Value::Float(u.iter().zip(&v).map(|(a, b)| a * b).sum()))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn cross(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let u = list("cross", arguments[0].eval(context, None, depth)?)?;
+    let v = list("cross", arguments[1].eval(context, None, depth)?)?;
+    let (&[a1, a2, a3], &[b1, b2, b3]) = (&u[..], &v[..]) else {
+        return Err(during("cross", "only works on two lists of 3".to_string()));
+    };
+    Ok(from_list(vec![
+        a2 * b3 - a3 * b2,
+        a3 * b1 - a1 * b3,
+        a1 * b2 - a2 * b1,
+    ]))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn identity(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let n = arguments[0].eval(context, None, depth)?.as_int()?;
+    if !(1..=100).contains(&n) {
+        return Err(during(
+            "identity",
+            format!("{} isn't a size from 1 to 100", n),
+        ));
+    }
+    Ok(from_matrix(unit(n as usize)))
+}
+// at(v, i) is the i-th value of a list, at(m, i, j) the j-th value in the i-th row, and at(m, i) the whole row
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn at(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let mut value = arguments[0].eval(context, None, depth)?;
+    for index in &arguments[1..] {
+        let i = index.eval(context, None, depth)?.as_int()?;
+        let items = match value {
+            Value::Vector(items) => items,
+            _ => return Err(during("at", "too many indices".to_string())),
+        };
+        let len = items.len();
+        value = usize::try_from(i - 1)
+            .ok()
+            .and_then(|i| items.into_iter().nth(i))
+            .ok_or_else(|| during("at", format!("{} isn't an index from 1 to {}", i, len)))?;
+    }
+    Ok(value)
+}
+
+// An error if the query multiplies two matrices with * or raises one to a power with ^, before it's worked out.
+// Declarations are checked too, apart from functions, whose parameters aren't known until they're called
+pub fn check_query(query: &str, context: &Context) -> EvalResult<()> {
+    if !query.contains(['*', '^']) {
+        return Ok(());
+    }
+    let chars: Vec<char> = query.chars().collect();
+    let body: String = match func::assignment(query) {
+        Some(i) if chars[..i].contains(&'(') => return Ok(()),
+        Some(i) => chars[i + 1..].iter().collect(),
+        None => query.to_string(),
+    };
+    match func::parse(&body, context) {
+        Some(expr) => check_products(&expr, context),
+        None => Ok(()),
+    }
+}
+// Only the operands that could be lists at all get worked out to see, and anything that can't be worked out
+// on its own (like the k in sum(k * m, k, 1, 3)) is left for num_parser to deal with
+fn check_products(expr: &Expression, context: &Context) -> EvalResult<()> {
+    let value = |e: &Expression| e.eval(context, None, 0).ok();
+    let could_be_list = |e: &Expression| maybe_list(e, context, &mut Vec::new());
+    match expr {
+        Expression::Binary(lhs, token, rhs) => {
+            check_products(lhs, context)?;
+            check_products(rhs, context)?;
+            match &*token.to_string() {
+                "*" if could_be_list(lhs) && could_be_list(rhs) => {
+                    if let (Some(a), Some(b)) = (value(lhs), value(rhs)) {
+                        let both_lists = matches!((&a, &b), (Value::Vector(_), Value::Vector(_)));
+                        if both_lists && (is_matrix(&a) || is_matrix(&b)) {
+                            return Err(during(
+                                "*",
+                                "it multiplies matrices element by element, matmul(a, b) is the matrix product".to_string(),
+                            ));
+                        }
+                    }
+                    Ok(())
+                }
+                "^" if could_be_list(lhs) => match value(lhs) {
+                    Some(m) if is_matrix(&m) => Err(during(
+                        "^",
+                        "it raises each element of a matrix, multiply it by itself with matmul instead".to_string(),
+                    )),
+                    _ => Ok(()),
+                },
+                _ => Ok(()),
+            }
+        }
+        Expression::Unary(_, inner) => check_products(inner, context),
+        Expression::Func(_, args) | Expression::Union(args) => {
+            args.iter().try_for_each(|arg| check_products(arg, context))
+        }
+        _ => Ok(()),
+    }
+}
+// Whether an expression might come out as a list, without working it out. seen keeps a function
+// that calls itself from being looked into forever
+fn maybe_list(expr: &Expression, context: &Context, seen: &mut Vec<String>) -> bool {
+    match expr {
+        Expression::Literal(value) => matches!(value, Value::Vector(_)),
+        Expression::Union(_) => true,
+        Expression::Var(name) if !seen.contains(name) => match context.get_var(name) {
+            Some(body) => {
+                seen.push(name.clone());
+                maybe_list(&body, context, seen)
+            }
+            None => false,
+        },
+        Expression::Func(name, args) => {
+            let gives_list = matches!(
+                name.as_str(),
+                "list" | "transpose" | "inv" | "matmul" | "solve" | "cross" | "identity" | "at"
+            );
+            let body = match context.get_function(name) {
+                Some((_, body)) if !seen.contains(name) => {
+                    seen.push(name.clone());
+                    maybe_list(&body, context, seen)
+                }
+                _ => false,
+            };
+            gives_list || body || args.iter().any(|arg| maybe_list(arg, context, seen))
+        }
+        Expression::Binary(lhs, _, rhs) => {
+            maybe_list(lhs, context, seen) || maybe_list(rhs, context, seen)
+        }
+        Expression::Unary(_, inner) => maybe_list(inner, context, seen),
+        _ => false,
+    }
+}
+
+fn during(name: &'static str, message: String) -> ErrorType {
+    ErrorType::ErrorDuring {
+        operation_name: name,
+        error: Box::new(ErrorType::InternalError { message }),
+    }
+}
+fn is_matrix(value: &Value) -> bool {
+    matches!(value, Value::Vector(rows) if rows.iter().all(|r| matches!(r, Value::Vector(_))))
+}
+fn list(name: &'static str, value: Value) -> EvalResult<Vec<f64>> {
+    if is_matrix(&value) {
+        return Err(during(name, "needs a list, not a matrix".to_string()));
+    }
+    value.as_vector().iter().map(|v| v.as_float()).collect()
+}
+// A plain list is taken as one row, or one column if column is set. Rows all have to be the same length
+fn matrix(name: &'static str, value: Value, column: bool) -> EvalResult<Matrix> {
+    let m: Matrix = if is_matrix(&value) {
+        value
+            .as_vector()
+            .iter()
+            .map(|row| row.as_vector().iter().map(|v| v.as_float()).collect())
+            .collect::<EvalResult<Matrix>>()?
+    } else if column {
+        list(name, value)?.into_iter().map(|x| vec![x]).collect()
+    } else {
+        vec![list(name, value)?]
+    };
+    let width = m.first().map_or(0, Vec::len);
+    if width == 0 || m.iter().any(|row| row.len() != width) {
+        return Err(during(name, "rows have to be the same length".to_string()));
+    }
+    Ok(m)
+}
+fn square(name: &'static str, value: Value) -> EvalResult<Matrix> {
+    let m = matrix(name, value, false)?;
+    if m.len() != m[0].len() {
+        return Err(during(
+            name,
+            format!("needs a square matrix, not {}×{}", m.len(), m[0].len()),
+        ));
+    }
+    Ok(m)
+}
+fn unit(n: usize) -> Matrix {
+    (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect()
+}
+fn flip(m: &Matrix) -> Matrix {
+    (0..m[0].len())
+        .map(|j| m.iter().map(|row| row[j]).collect())
+        .collect()
+}
+fn from_list(list: Vec<f64>) -> Value {
+    Value::Vector(list.into_iter().map(Value::Float).collect())
+}
+fn from_matrix(m: Matrix) -> Value {
+    Value::Vector(m.into_iter().map(from_list).collect())
+}
+// Gauss-Jordan elimination with partial pivoting, doing the same row operations to b alongside.
+// Gives back the determinant of a and what b ends up as - a⁻¹b, or a⁻¹ when b starts as the identity.
+// A zero determinant means b is left half done and shouldn't be used.
+// Rounding error means a singular matrix like [[1,2,3],[4,5,6],[7,8,9]] hardly ever gets an exact 0 pivot,
+// so anything that small next to the biggest entry counts as 0
+fn eliminate(mut a: Matrix, mut b: Matrix) -> (f64, Matrix) {
+    let n = a.len();
+    let biggest = a.iter().flatten().fold(0.0f64, |big, x| big.max(x.abs()));
+    let mut determinant = 1.0;
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+            .unwrap_or(col);
+        if a[pivot][col].abs() <= PIVOT_TOLERANCE * biggest {
+            return (0.0, b);
+        }
+        if pivot != col {
+            a.swap(pivot, col);
+            if !b.is_empty() {
+                b.swap(pivot, col);
+            }
+            determinant = -determinant;
+        }
+        let p = a[col][col];
+        determinant *= p;
+        for row in 0..n {
+            if row == col {
+                continue;
+            }
+            let factor = a[row][col] / p;
+            if factor == 0.0 {
+                continue;
+            }
+            let (pivot_a, pivot_b) = (a[col].clone(), b.get(col).cloned().unwrap_or_default());
+            for (x, p) in a[row].iter_mut().zip(&pivot_a) {
+                *x -= factor * p;
+            }
+            for (x, p) in b.get_mut(row).into_iter().flatten().zip(&pivot_b) {
+                *x -= factor * p;
+            }
+        }
+    }
+    for (row, b_row) in b.iter_mut().enumerate() {
+        for x in b_row.iter_mut() {
+            *x /= a[row][row];
+        }
+    }
+    (determinant, b)
+}
+
+// The Output pane shows a matrix as a grid with its columns lined up, show() formats each number
+pub fn grid(value: &Value, show: impl Fn(&Value) -> String) -> Option<String> {
+    let rows = cells(value, show)?;
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|j| {
+            rows.iter()
+                .map(|row| row[j].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let padded: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:>1$}", cell, width))
+                .collect();
+            format!("│ {} │", padded.join("  "))
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+// and the Variables pane gives the size, then the rows one after another
+pub fn compact(value: &Value, show: impl Fn(&Value) -> String) -> Option<String> {
+    let rows = cells(value, show)?;
+    let joined: Vec<String> = rows.iter().map(|row| row.join(" ")).collect();
+    Some(format!(
+        "{}×{} [{}]",
+        rows.len(),
+        rows[0].len(),
+        joined.join(" | ")
+    ))
+}
+fn cells(value: &Value, show: impl Fn(&Value) -> String) -> Option<Vec<Vec<String>>> {
+    let Value::Vector(rows) = value else {
+        return None;
+    };
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| match row {
+            Value::Vector(items) => Some(items.iter().map(&show).collect()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let width = rows.first()?.len();
+    (width > 0 && rows.iter().all(|row| row.len() == width)).then_some(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{stats, testing};
+
+    fn eval(query: &str) -> EvalResult<Value> {
+        testing::eval(&stats::preprocess(query))
+    }
+    fn grid_of(query: &str) -> Matrix {
+        matrix("test", eval(query).unwrap(), true).unwrap()
+    }
+    fn near(a: &Matrix, b: &Matrix) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .flatten()
+                .zip(b.iter().flatten())
+                .all(|(x, y)| (x - y).abs() < 1e-9)
+    }
+
+    #[test]
+    fn determinants() {
+        assert!(testing::close(
+            &stats::preprocess("det([[1,2],[3,4]])"),
+            -2.0
+        ));
+        assert!(testing::close(
+            &stats::preprocess("det([[0,1,0],[1,0,0],[0,0,2]])"),
+            -2.0
+        ));
+        assert!(testing::close(
+            &stats::preprocess("det([[1,2],[2,4]])"),
+            0.0
+        ));
+        assert!(eval("det([[1,2,3],[4,5,6]])").is_err());
+    }
+
+    #[test]
+    fn inverses() {
+        assert!(near(
+            &grid_of("inv([[4,7],[2,6]])"),
+            &vec![vec![0.6, -0.7], vec![-0.2, 0.4]]
+        ));
+        assert!(near(
+            &grid_of("matmul([[2,1],[1,3]], inv([[2,1],[1,3]]))"),
+            &unit(2)
+        ));
+        assert!(eval("inv([[1,2],[2,4]])").is_err());
+    }
+
+    #[test]
+    fn singular_after_rounding_error() {
+        // rounding error leaves a pivot of about 1e-16 here rather than 0
+        let singular = "[[1,2,3],[4,5,6],[7,8,9]]";
+        assert_eq!(
+            eval(&format!("det({})", singular)).unwrap(),
+            Value::Float(0.0)
+        );
+        assert!(eval(&format!("inv({})", singular)).is_err());
+        assert!(eval(&format!("solve({}, [1,2,3])", singular)).is_err());
+        // small numbers on their own are fine, it's relative to the biggest entry
+        assert!(near(
+            &grid_of("inv([[0.000001,0],[0,0.000002]])"),
+            &vec![vec![1000000.0, 0.0], vec![0.0, 500000.0]]
+        ));
+    }
+
+    #[test]
+    fn solving() {
+        // 2x + y = 5, x + 3y = 10
+        assert!(near(
+            &grid_of("solve([[2,1],[1,3]], [5,10])"),
+            &vec![vec![1.0], vec![3.0]]
+        ));
+        assert!(eval("solve([[1,2],[2,4]], [1,2])").is_err());
+        assert!(eval("solve([[2,1],[1,3]], [1,2,3])").is_err());
+    }
+
+    #[test]
+    fn products() {
+        assert!(near(
+            &grid_of("matmul([[1,2],[3,4]], [[5,6],[7,8]])"),
+            &vec![vec![19.0, 22.0], vec![43.0, 50.0]]
+        ));
+        // a list on the right is a column, and gives a list back
+        assert!(near(
+            &grid_of("matmul([[1,2],[3,4]], [1,1])"),
+            &vec![vec![3.0], vec![7.0]]
+        ));
+        assert!(eval("matmul([[1,2,3]], [[1,2,3]])").is_err());
+        assert!(testing::close(
+            &stats::preprocess("dot([1,2,3], [4,5,6])"),
+            32.0
+        ));
+        assert!(near(
+            &grid_of("cross([1,0,0], [0,1,0])"),
+            &vec![vec![0.0], vec![0.0], vec![1.0]]
+        ));
+        assert!(near(
+            &grid_of("transpose([[1,2,3]])"),
+            &vec![vec![1.0], vec![2.0], vec![3.0]]
+        ));
+    }
+
+    #[test]
+    fn matrix_products_point_at_matmul() {
+        let mut context = testing::context();
+        num_parser::eval_with_mutable_context(
+            &stats::preprocess("A = [[1,2],[3,4]]"),
+            &mut context,
+        )
+        .unwrap();
+        let check = |query: &str| check_query(&stats::preprocess(query), &context);
+        let err = check("[[1,2],[3,4]] * [[1,0],[0,1]]").unwrap_err();
+        assert!(err.to_string().contains("matmul"));
+        assert!(check("A*A").is_err());
+        assert!(check("A^2").is_err());
+        assert!(check("det(A*A)").is_err());
+        assert!(check("B = A*A").is_err());
+        // scaling, lists, and things that can't be known until they're worked out are left alone
+        assert!(check("2*A").is_ok());
+        assert!(check("A*2 + A").is_ok());
+        assert!(check("[1,2]*[3,4]").is_ok());
+        assert!(check("[1,2]^2").is_ok());
+        assert!(check("f(x) = x*x").is_ok());
+        assert!(check("sum(k*A, k, 1, 3)").is_ok());
+    }
+
+    #[test]
+    fn indexing() {
+        assert!(testing::close(
+            &stats::preprocess("[[1,2],[3,4]][2,1]"),
+            3.0
+        ));
+        assert!(near(
+            &grid_of("identity(2)[2]"),
+            &vec![vec![0.0], vec![1.0]]
+        ));
+        assert!(eval("[1,2][3]").is_err());
+        assert!(eval("[1,2][1,1]").is_err());
+    }
+
+    #[test]
+    fn grid_lines_up_columns() {
+        let m = eval("[[1,20],[300,4]]").unwrap();
+        let show = |v: &Value| v.to_string();
+        assert_eq!(grid(&m, show).unwrap(), "│   1  20 │\n│ 300   4 │");
+        assert_eq!(compact(&m, show).unwrap(), "2×2 [1 20 | 300 4]");
+        assert_eq!(grid(&Value::Int(1), show), None);
+    }
+}
@@ -0,0 +1,307 @@
+// clicking and scrolling. What's under the pointer is worked out from the same layout the panes get drawn with (layout.rs),
+// and which entry a click hit from how many rows each one wraps to at the pane's width
+use crate::app::{constants, func, layout, lib::AppMode, App, OPTIONS_TEXT};
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
+
+// how many rows one notch of the wheel moves
+const WHEEL_ROWS: u16 = 3;
+
+#[derive(Clone, Copy)]
+enum List {
+    Vars,
+    Funcs,
+    Constants,
+    History,
+}
+
+impl App {
+    pub(super) fn handle_mouse(&mut self, mouse_event: MouseEvent) {
+        // same as the keyboard, nothing happens until the query being worked out is done with
+        if self.job.is_some() {
+            return;
+        }
+        let (col, row) = (mouse_event.column, mouse_event.row);
+        match mouse_event.kind {
+            MouseEventKind::ScrollUp => self.wheel(col, row, false),
+            MouseEventKind::ScrollDown => self.wheel(col, row, true),
+            MouseEventKind::Down(MouseButton::Left) => self.click(col, row),
+            _ => (),
+        }
+    }
+    fn wheel(&mut self, col: u16, row: u16, down: bool) {
+        let panes = self.panes(self.screen);
+        // the amortization table scrolls by payment, same as <Up>/<Down>
+        if matches!(self.mode, AppMode::AmortizationView) && hit(panes.middle, col, row) {
+            self.schedule_pos = match down {
+                true => (self.schedule_pos + 1).min(self.schedule.len().saturating_sub(1)),
+                false => self.schedule_pos.saturating_sub(1),
+            };
+            return;
+        }
+        let Some((list, pane)) = self.list_at(col, row) else {
+            return;
+        };
+        let max = layout::max_scroll(&self.entries(list).concat(), pane);
+        let offset = self.offset(list);
+        // it might have been further down before the list got shorter
+        *offset = (*offset).min(max);
+        *offset = match down {
+            true => (*offset + WHEEL_ROWS).min(max),
+            false => offset.saturating_sub(WHEEL_ROWS),
+        };
+    }
+    fn click(&mut self, col: u16, row: u16) {
+        let panes = self.panes(self.screen);
+        // in the compact layout, a click on the tab bar switches tabs whatever else is going on
+        if let Some(bar) = panes.tabs.filter(|bar| hit(*bar, col, row)) {
+            let tabs = layout::tabs(&self.layout, self.display.constants);
+            if let Some(tab) = layout::tab_at(bar, &tabs, col) {
+                self.tab = tab;
+            }
+            return;
+        }
+        // the one line layout has the input and the answer side by side, there's nothing there to click
+        if layout::minimal(self.screen) && self.mode == AppMode::Normal {
+            return;
+        }
+        match self.mode {
+            AppMode::Option if hit(panes.input, col, row) => self.click_option(panes.input, row),
+            AppMode::Normal if hit(panes.input, col, row) => {
+                self.click_input(panes.input, col, row)
+            }
+            AppMode::Normal => match self.list_at(col, row) {
+                Some((List::History, pane)) => {
+                    if let Some(n) = self.entry_at(List::History, pane, row) {
+                        // loads it just like scrolling up to it would, <Esc> still gets back what was being typed
+                        self.history.try_store(self.input.get_text());
+                        self.history.select(n);
+                        if let Some(s) = self.history.curr() {
+                            self.input.replace(s);
+                        }
+                    }
+                }
+                Some((List::Vars, pane)) => {
+                    if let Some(name) = self.var_at(pane, row) {
+                        self.input.insert_str(&name);
+                    }
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    // the input is the line after the first, centered, so the cursor goes however far into it the click was
+    fn click_input(&mut self, pane: Rect, col: u16, row: u16) {
+        let inner = layout::inner(pane);
+        let text = format!(":> {}", self.input.get_text());
+        let width = text.chars().count() as u16;
+        // a long input wraps, and then there's no telling which line is which from here
+        if row != inner.y + 1 || width > inner.width {
+            return;
+        }
+        let start = inner.x + (inner.width - width) / 2 + 3;
+        self.input.set_cursor(col.saturating_sub(start) as usize);
+    }
+    // every line of the Options pane ends in the key for it, e.g. "Angle unit <d>", so a click presses that key
+    fn click_option(&mut self, pane: Rect, row: u16) {
+        let inner = layout::inner(pane);
+        let Some(row) = row.checked_sub(inner.y) else {
+            return;
+        };
+        let mut top = 0;
+        for line in OPTIONS_TEXT.lines() {
+            let height = layout::rows(line, inner.width).max(1);
+            if (top..top + height).contains(&(row as usize)) {
+                let key = line
+                    .trim_end()
+                    .strip_suffix('>')
+                    .and_then(|rest| rest.chars().last());
+                if let Some(key) = key {
+                    self.handle_key_event(KeyEvent::from(KeyCode::Char(key)));
+                }
+                return;
+            }
+            top += height;
+        }
+    }
+    // a variable's lines start with its name, except the "n = ..., mean = ..." summary under a list,
+    // which is indented and belongs to the list above it
+    fn var_at(&self, pane: Rect, row: u16) -> Option<String> {
+        let n = self.entry_at(List::Vars, pane, row)?;
+        let lines = self.vars_cache.lines();
+        let line = lines[..=n]
+            .iter()
+            .rev()
+            .find(|line| !line.starts_with("\n "))?;
+        let (name, _) = line.trim_start_matches('\n').split_once(" = ")?;
+        Some(name.to_string())
+    }
+    fn list_at(&self, col: u16, row: u16) -> Option<(List, Rect)> {
+        let panes = self.panes(self.screen);
+        let lists = [
+            (List::Vars, panes.vars),
+            (List::Funcs, panes.funcs),
+            (List::Constants, panes.constants),
+            (List::History, panes.history),
+        ];
+        lists
+            .into_iter()
+            .filter_map(|(list, pane)| Some((list, pane?)))
+            .find(|(_, pane)| hit(*pane, col, row))
+    }
+    fn entries(&self, list: List) -> Vec<String> {
+        match list {
+            List::Vars => self.vars_cache.lines().to_vec(),
+            List::Funcs => func::funcs_to_strings(&self.ctxt),
+            List::Constants => constants::to_strings(),
+            List::History => self.history.render_all(&self.ctxt, &self.display),
+        }
+    }
+    fn offset(&mut self, list: List) -> &mut u16 {
+        match list {
+            List::Vars => &mut self.scroll.vars,
+            List::Funcs => &mut self.scroll.funcs,
+            List::Constants => &mut self.scroll.constants,
+            List::History => &mut self.scroll.history,
+        }
+    }
+    // which entry of a list is on a row of the screen, taking into account how far it's scrolled
+    fn entry_at(&self, list: List, pane: Rect, row: u16) -> Option<usize> {
+        let entries = self.entries(list);
+        let inner = layout::inner(pane);
+        let scroll = match list {
+            List::Vars => self.scroll.vars,
+            List::Funcs => self.scroll.funcs,
+            List::Constants => self.scroll.constants,
+            List::History => self.scroll.history,
+        };
+        let scroll = scroll.min(layout::max_scroll(&entries.concat(), pane));
+        let row = row.checked_sub(inner.y)? + scroll;
+        layout::entry_at(&entries, inner.width, row as usize)
+    }
+}
+
+fn hit(pane: Rect, col: u16, row: u16) -> bool {
+    (pane.x..pane.x + pane.width).contains(&col) && (pane.y..pane.y + pane.height).contains(&row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        lib::Setting,
+        testing::{self, type_in},
+    };
+    use crossterm::event::KeyModifiers;
+
+    fn mouse(app: &mut App, kind: MouseEventKind, column: u16, row: u16) {
+        app.handle_mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+    }
+    fn click(app: &mut App, column: u16, row: u16) {
+        mouse(app, MouseEventKind::Down(MouseButton::Left), column, row);
+    }
+
+    #[test]
+    fn hit_includes_the_border() {
+        let pane = Rect::new(10, 5, 4, 3);
+        assert!(hit(pane, 10, 5));
+        assert!(hit(pane, 13, 7));
+        assert!(!hit(pane, 14, 7));
+        assert!(!hit(pane, 13, 8));
+        assert!(!hit(pane, 9, 5));
+    }
+
+    #[test]
+    fn clicking_history_loads_the_query() {
+        let mut app = testing::app();
+        type_in(&mut app, "1+1");
+        type_in(&mut app, "2+3");
+        // newest first, under the empty first row
+        click(&mut app, 80, 2);
+        assert_eq!(app.input.get_text(), "2+3");
+        click(&mut app, 80, 3);
+        assert_eq!(app.input.get_text(), "1+1");
+        // below the last entry there's nothing to load
+        click(&mut app, 80, 20);
+        assert_eq!(app.input.get_text(), "1+1");
+    }
+
+    #[test]
+    fn clicking_a_variable_types_its_name() {
+        let mut app = testing::app();
+        type_in(&mut app, "x = 5");
+        type_in(&mut app, "data = [1,2,3]");
+        "2*".chars()
+            .for_each(|c| testing::press(&mut app, KeyCode::Char(c)));
+        // every line fits on one row, under the empty first one
+        let lines = app.vars_cache.lines().to_vec();
+        let row_of =
+            |start: &str| lines.iter().position(|l| l.starts_with(start)).unwrap() as u16 + 2;
+        click(&mut app, 5, row_of("\nx ="));
+        assert_eq!(app.input.get_text(), "2*x");
+        // the summary under a list belongs to the list
+        click(&mut app, 5, row_of("\n "));
+        assert_eq!(app.input.get_text(), "2*xdata");
+    }
+
+    #[test]
+    fn clicking_an_option_presses_its_key() {
+        let mut app = testing::app();
+        testing::press(&mut app, KeyCode::Tab);
+        let input = app.panes(app.screen).input;
+        // the first row inside the border is empty, then "Angle unit <d>"
+        click(&mut app, 50, input.y + 2);
+        assert_eq!(app.mode, AppMode::Select(Setting::Angle));
+    }
+
+    #[test]
+    fn clicking_the_input_moves_the_cursor() {
+        let mut app = testing::app();
+        "1234"
+            .chars()
+            .for_each(|c| testing::press(&mut app, KeyCode::Char(c)));
+        // ":> 1234" is 7 wide, centred in the 48 inside the input pane
+        let inner = layout::inner(app.panes(app.screen).input);
+        let start = inner.x + (inner.width - 7) / 2 + 3;
+        click(&mut app, start + 2, inner.y + 1);
+        testing::press(&mut app, KeyCode::Char('+'));
+        assert_eq!(app.input.get_text(), "12+34");
+    }
+
+    #[test]
+    fn the_wheel_stops_at_the_ends() {
+        let mut app = testing::app();
+        mouse(&mut app, MouseEventKind::ScrollDown, 5, 30);
+        // the constants don't fit in their pane, so they scroll
+        assert_eq!(app.scroll.constants, WHEEL_ROWS);
+        for _ in 0..100 {
+            mouse(&mut app, MouseEventKind::ScrollDown, 5, 30);
+        }
+        let pane = app.panes(app.screen).constants.unwrap();
+        let max = layout::max_scroll(&constants::to_strings().concat(), pane);
+        assert_eq!(app.scroll.constants, max);
+        mouse(&mut app, MouseEventKind::ScrollUp, 5, 30);
+        assert_eq!(app.scroll.constants, max - WHEEL_ROWS);
+        // the history's empty so it doesn't go anywhere
+        mouse(&mut app, MouseEventKind::ScrollDown, 80, 5);
+        assert_eq!(app.scroll.history, 0);
+    }
+
+    #[test]
+    fn clicking_a_tab_in_the_compact_layout() {
+        let mut app = testing::app();
+        app.screen = Rect::new(0, 0, 60, 30);
+        // Variables, then Functions from column 16
+        click(&mut app, 20, 0);
+        assert_eq!(app.tab, layout::Pane::Funcs);
+        // History is last, and goes right to the edge
+        click(&mut app, 59, 0);
+        assert_eq!(app.tab, layout::Pane::History);
+    }
+}
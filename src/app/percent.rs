@@ -0,0 +1,150 @@
+// percentages the way a desk calculator does them, "200 + 15%" is 230, "200 - 15%" is 170 and "50% of 80" is 40
+// num_parser only knows % as the remainder, so a % with nothing after it (or an operator, a closing bracket or "of")
+// gets rewritten into plain arithmetic before it sees the query, while 7 % 3 is still the remainder
+// - x% on its own is x/100, so 2 * 15% is 0.3
+// - adding or taking away a percentage scales whatever's to the left of it, at the same bracket level
+// - "of" only means anything straight after a percentage, and multiplies
+use crate::app::{complex, func};
+
+pub fn preprocess(query: &str) -> String {
+    // for declarations like tip = 15% of bill only the right hand side gets rewritten
+    let chars: Vec<char> = query.chars().collect();
+    match func::assignment(query) {
+        Some(i) => {
+            let (name, body): (String, String) =
+                (chars[..i].iter().collect(), chars[i + 1..].iter().collect());
+            format!("{}={}", name, rewrite(&body))
+        }
+        None => rewrite(query),
+    }
+}
+
+fn rewrite(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut out: Vec<char> = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let after = next_word(&chars, i + 1);
+        let postfix = match chars[i] {
+            '%' => match chars.get(after) {
+                None => true,
+                Some(c) => ")+-*/^,".contains(*c) || starts_of(&chars, after),
+            },
+            _ => false,
+        };
+        let operand = postfix
+            .then(|| complex::left_operand(&out, out.len()))
+            .flatten();
+        let Some(start) = operand else {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        };
+        let value: String = out.drain(start..).collect::<String>().trim().to_string();
+        i += 1;
+        if starts_of(&chars, after) {
+            out.extend(format!("({}/100)*", value).chars());
+            i = after + 2;
+            continue;
+        }
+        match scaled(&out) {
+            Some((level, op)) => {
+                let base: String = out.drain(level..).collect();
+                // the base went up to and including the + or -
+                let base = base.trim_end().trim_end_matches(['+', '-']).trim();
+                out.extend(format!(" ({})*(1{}({})/100)", base, op, value).chars());
+            }
+            None => out.extend(format!("({}/100)", value).chars()),
+        }
+    }
+    out.into_iter().collect()
+}
+// the first thing that isn't a space from `at` on
+fn next_word(chars: &[char], at: usize) -> usize {
+    let mut at = at;
+    while chars.get(at) == Some(&' ') {
+        at += 1;
+    }
+    at
+}
+// "of" as a whole word
+fn starts_of(chars: &[char], at: usize) -> bool {
+    chars.get(at..at + 2) == Some(&['o', 'f'])
+        && !chars.get(at + 2).is_some_and(|c| c.is_alphanumeric())
+}
+// When the percentage is being added to or taken from something, where that something starts and which it is.
+// It starts after the nearest unclosed bracket, comma or comparison, so (a + b + 10%) scales a + b
+fn scaled(out: &[char]) -> Option<(usize, char)> {
+    let end = out.iter().rposition(|c| *c != ' ')?;
+    let op = out[end];
+    if op != '+' && op != '-' {
+        return None;
+    }
+    // a sign with nothing before it, like (-10%), isn't adding to anything
+    let before = out[..end].iter().rposition(|c| *c != ' ')?;
+    if !(out[before].is_alphanumeric() || matches!(out[before], ')' | '.' | '!')) {
+        return None;
+    }
+    let mut depth = 0;
+    let mut level = 0;
+    for (i, c) in out[..end].iter().enumerate().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth == 0 => {
+                level = i + 1;
+                break;
+            }
+            '(' => depth -= 1,
+            ',' | '=' | '<' | '>' | '&' | '|' if depth == 0 => {
+                level = i + 1;
+                break;
+            }
+            _ => (),
+        }
+    }
+    Some((level, op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(query: &str) -> f64 {
+        num_parser::eval(&preprocess(query))
+            .unwrap()
+            .as_float()
+            .unwrap()
+    }
+
+    #[test]
+    fn desk_calculator_percentages() {
+        assert!((eval("200 + 15%") - 230.0).abs() < 1e-9);
+        assert!((eval("200 - 15%") - 170.0).abs() < 1e-9);
+        assert!((eval("50% of 80") - 40.0).abs() < 1e-9);
+        assert!((eval("2 * 15%") - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn remainder_still_works() {
+        assert_eq!(preprocess("7 % 3"), "7 % 3");
+        assert!((eval("7 % 3") - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scales_everything_at_the_same_bracket_level() {
+        assert!((eval("(100 + 100 + 10%) * 2") - 440.0).abs() < 1e-9);
+        assert!((eval("max(100 + 10%, 50)") - 110.0).abs() < 1e-9);
+        // a sign with nothing before it isn't adding to anything
+        assert!((eval("(-10%)") + 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn of_has_to_be_a_whole_word() {
+        assert_eq!(preprocess("5%offset"), "5%offset");
+    }
+
+    #[test]
+    fn only_the_declared_value_is_rewritten() {
+        assert_eq!(preprocess("tip = 15% of bill"), "tip = (15/100)* bill");
+    }
+}
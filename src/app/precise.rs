@@ -0,0 +1,440 @@
+// arbitrary precision evaluation, for when 16 decimal places of f64 isn't enough
+// numbers are big integers with an implied decimal point, n means n / 10^scale, and the scale is
+// the requested number of decimal places plus some guard digits to soak up rounding errors along the way.
+// The irrational functions are all plain series, made to converge quickly by shrinking the argument first:
+// - exp: halve x until it's tiny, sum the Taylor series, then square back up
+// - ln: pull out powers of 2, then ln(m) = 2 atanh((m-1)/(m+1))
+// - sin/cos: reduce into [-pi, pi] and sum the Taylor series
+// - atan: the half angle formula until x is small, then the Taylor series. pi comes from Machin's formula
+// The tree walking itself happens in walker.rs, same as exact mode
+use crate::app::walker::{self, Engine};
+use num::{BigInt, Integer, Signed, ToPrimitive, Zero};
+use num_parser::{settings::AngleUnit, Context, Expression, Value};
+
+// the repeated squaring in exp() and the like can eat a handful of digits, so compute a few more than we show
+const GUARD_DIGITS: u32 = 20;
+// Past a thousand digits answers, and redoing the Variables pane after every query, get noticeably slow
+pub const MAX_DIGITS: u32 = 1000;
+const MAX_EXP: i64 = 10_000;
+
+// Evaluates to `digits` decimal places, giving the result as a string ready for display
+pub fn eval(expr: &Expression, context: &Context, digits: u32) -> Option<String> {
+    let engine = Precise::new(digits);
+    walker::eval(&engine, expr, context).map(|n| engine.format(&n, digits))
+}
+
+struct Precise {
+    scale: u32,
+    one: BigInt,
+    pi: BigInt,
+}
+impl Precise {
+    fn new(digits: u32) -> Self {
+        let scale = digits.min(MAX_DIGITS) + GUARD_DIGITS;
+        let one = num::pow(BigInt::from(10), scale as usize);
+        // Machin: pi = 16 atan(1/5) - 4 atan(1/239)
+        let pi = atan_inverse(&one, 5) * 16 - atan_inverse(&one, 239) * 4;
+        Precise { scale, one, pi }
+    }
+    fn mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        a * b / &self.one
+    }
+    fn div(&self, a: &BigInt, b: &BigInt) -> Option<BigInt> {
+        if b.is_zero() {
+            None
+        } else {
+            Some(a * &self.one / b)
+        }
+    }
+    fn is_whole(&self, n: &BigInt) -> bool {
+        (n % &self.one).is_zero()
+    }
+    fn sqrt(&self, x: &BigInt) -> Option<BigInt> {
+        // negative square roots are complex, num_parser can have those
+        if x.is_negative() {
+            None
+        } else {
+            Some((x * &self.one).sqrt())
+        }
+    }
+    fn exp(&self, x: &BigInt) -> Option<BigInt> {
+        // e^10000 already has over 4000 digits before the decimal point, anything bigger just hangs
+        if x.abs() > self.integer(MAX_EXP) {
+            return None;
+        }
+        if x.is_negative() {
+            return self.div(&self.one, &self.exp(&-x)?);
+        }
+        let small = &self.one / 1000;
+        let mut r = x.clone();
+        let mut halvings = 0;
+        while r > small {
+            r /= 2;
+            halvings += 1;
+        }
+        let mut sum = self.one.clone();
+        let mut term = self.one.clone();
+        let mut k = 1u32;
+        while !term.is_zero() {
+            term = self.mul(&term, &r) / k;
+            sum += &term;
+            k += 1;
+        }
+        for _ in 0..halvings {
+            sum = self.mul(&sum, &sum);
+        }
+        Some(sum)
+    }
+    fn ln(&self, x: &BigInt) -> Option<BigInt> {
+        if !x.is_positive() {
+            return None;
+        }
+        // x = m * 2^k with m somewhere in [0.5, 1]
+        let mut m = x.clone();
+        let mut k: i64 = 0;
+        while m > self.one {
+            m /= 2;
+            k += 1;
+        }
+        let half = &self.one / 2;
+        while m < half {
+            m *= 2;
+            k -= 1;
+        }
+        let z = self.div(&(&m - &self.one), &(&m + &self.one))?;
+        let ln2 = self.atanh_series(&(&self.one / 3)) * 2;
+        Some(self.atanh_series(&z) * 2 + ln2 * k)
+    }
+    fn atanh_series(&self, z: &BigInt) -> BigInt {
+        // z + z^3/3 + z^5/5 + ..., only used with |z| <= 1/3 so it converges quickly enough
+        let z2 = self.mul(z, z);
+        let mut power = z.clone();
+        let mut sum = BigInt::zero();
+        let mut k = 1u32;
+        while !power.is_zero() {
+            sum += &power / k;
+            power = self.mul(&power, &z2);
+            k += 2;
+        }
+        sum
+    }
+    fn atan(&self, x: &BigInt) -> BigInt {
+        if x.abs() > self.one {
+            // atan(x) = ±pi/2 - atan(1/x)
+            let half_pi: BigInt = &self.pi / 2;
+            let inverse = self.div(&self.one, x).unwrap_or_default();
+            return if x.is_negative() {
+                -half_pi - self.atan(&inverse)
+            } else {
+                half_pi - self.atan(&inverse)
+            };
+        }
+        // atan(x) = 2 atan(x / (1 + sqrt(1 + x^2)))
+        let small = &self.one / 10;
+        let mut r = x.clone();
+        let mut doublings = 0;
+        while r.abs() > small {
+            let root = self
+                .sqrt(&(&self.one + self.mul(&r, &r)))
+                .unwrap_or_default();
+            r = self.div(&r, &(&self.one + root)).unwrap_or_default();
+            doublings += 1;
+        }
+        let r2 = self.mul(&r, &r);
+        let mut power = r.clone();
+        let mut sum = BigInt::zero();
+        let mut k = 1u32;
+        while !power.is_zero() {
+            if k % 4 == 1 {
+                sum += &power / k;
+            } else {
+                sum -= &power / k;
+            }
+            power = self.mul(&power, &r2);
+            k += 2;
+        }
+        sum << doublings
+    }
+    fn sin_cos(&self, x: &BigInt) -> (BigInt, BigInt) {
+        // reduce into [-pi, pi] first
+        let two_pi = &self.pi * 2;
+        let turns = (x + &self.pi).div_floor(&two_pi);
+        let r = x - turns * &two_pi;
+        let (mut sin, mut cos) = (BigInt::zero(), BigInt::zero());
+        let mut term = self.one.clone();
+        let mut n = 0u32;
+        // term is r^n / n!, alternating between the cos and sin series
+        while !term.is_zero() || n < 2 {
+            let sign = if (n / 2).is_multiple_of(2) { 1 } else { -1 };
+            if n.is_multiple_of(2) {
+                cos += &term * sign;
+            } else {
+                sin += &term * sign;
+            }
+            n += 1;
+            term = self.mul(&term, &r) / n;
+        }
+        (sin, cos)
+    }
+    fn to_radians(&self, x: &BigInt, unit: AngleUnit) -> BigInt {
+        match unit {
+            AngleUnit::Radian => x.clone(),
+            AngleUnit::Degree => self.mul(x, &self.pi) / 180,
+            AngleUnit::Turn => self.mul(x, &self.pi) * 2,
+        }
+    }
+    fn to_unit(&self, x: BigInt, unit: AngleUnit) -> Option<BigInt> {
+        match unit {
+            AngleUnit::Radian => Some(x),
+            AngleUnit::Degree => self.div(&(x * 180), &self.pi),
+            AngleUnit::Turn => self.div(&x, &(&self.pi * 2)),
+        }
+    }
+    fn power(&self, a: &BigInt, b: &BigInt) -> Option<BigInt> {
+        let whole = self.to_int(b).filter(|n| n.abs() <= MAX_EXP);
+        if let Some(n) = whole {
+            let mut result = self.one.clone();
+            let mut base = a.clone();
+            let mut e = n.unsigned_abs();
+            while e > 0 {
+                if e % 2 == 1 {
+                    result = self.mul(&result, &base);
+                }
+                base = self.mul(&base, &base);
+                e /= 2;
+            }
+            if n < 0 {
+                self.div(&self.one, &result)
+            } else {
+                Some(result)
+            }
+        } else if a.is_zero() && b.is_positive() {
+            Some(BigInt::zero())
+        } else {
+            // a^b = exp(b ln a), and ln() refuses negative a since that would be complex
+            self.exp(&self.mul(b, &self.ln(a)?))
+        }
+    }
+    // Rounds to `digits` decimal places, with trailing zeros trimmed like num_parser's own rounding
+    fn format(&self, n: &BigInt, digits: u32) -> String {
+        let drop = num::pow(BigInt::from(10), (self.scale - digits) as usize);
+        let half: BigInt = &drop / 2;
+        let rounded = if n.is_negative() {
+            -((-n + half) / &drop)
+        } else {
+            (n + half) / &drop
+        };
+        let text = rounded.abs().to_string();
+        let digits = digits as usize;
+        let padded = format!("{:0>width$}", text, width = digits + 1);
+        let (whole, frac) = padded.split_at(padded.len() - digits);
+        let frac = frac.trim_end_matches('0');
+        let sign = if rounded.is_negative() { "-" } else { "" };
+        if frac.is_empty() {
+            format!("{}{}", sign, whole)
+        } else {
+            format!("{}{}.{}", sign, whole, frac)
+        }
+    }
+}
+
+// atan(1/n) for a whole number n, which only needs divisions by small numbers
+fn atan_inverse(one: &BigInt, n: u32) -> BigInt {
+    let n2 = BigInt::from(n) * n;
+    let mut power = one / n;
+    let mut sum = BigInt::zero();
+    let mut k = 1u32;
+    while !power.is_zero() {
+        if k % 4 == 1 {
+            sum += &power / k;
+        } else {
+            sum -= &power / k;
+        }
+        power /= &n2;
+        k += 2;
+    }
+    sum
+}
+
+impl Engine for Precise {
+    type Num = BigInt;
+    fn literal(&self, val: &Value) -> Option<BigInt> {
+        match val {
+            Value::Int(i) => Some(self.integer(*i)),
+            // same trick as exact mode, 0.1 really means 1/10
+            Value::Float(f) => {
+                let r = crate::app::exact::decimal(*f)?;
+                Some(r.numer() * &self.one / r.denom())
+            }
+            _ => None,
+        }
+    }
+    fn constant(&self, name: &str) -> Option<BigInt> {
+        match name {
+            "pi" => Some(self.pi.clone()),
+            "tau" => Some(&self.pi * 2),
+            "e" => self.exp(&self.one),
+            "phi" => Some((&self.one + self.sqrt(&(&self.one * 5))?) / 2),
+            _ => None,
+        }
+    }
+    fn integer(&self, i: i64) -> BigInt {
+        BigInt::from(i) * &self.one
+    }
+    fn to_int(&self, n: &BigInt) -> Option<i64> {
+        if self.is_whole(n) {
+            (n / &self.one).to_i64()
+        } else {
+            None
+        }
+    }
+    fn negate(&self, n: BigInt) -> Option<BigInt> {
+        Some(-n)
+    }
+    fn binary(&self, op: &str, a: BigInt, b: BigInt) -> Option<BigInt> {
+        match op {
+            "+" => Some(a + b),
+            "-" => Some(a - b),
+            "*" => Some(self.mul(&a, &b)),
+            "/" => self.div(&a, &b),
+            "%" if b.is_zero() => None,
+            "%" => Some(a % b),
+            "^" => self.power(&a, &b),
+            _ => None,
+        }
+    }
+    fn function(&self, name: &str, args: &[BigInt], context: &Context) -> Option<BigInt> {
+        let unit = context.angle_unit;
+        // num_parser treats the hyperbolic functions as taking/returning angles too, so we do the same to match
+        let rad = |x: &BigInt| self.to_radians(x, unit);
+        match (name, args) {
+            ("abs", [x]) => Some(x.abs()),
+            ("floor", [x]) => Some(x.div_floor(&self.one) * &self.one),
+            ("ceil", [x]) => Some(-((-x).div_floor(&self.one)) * &self.one),
+            ("round", [x]) => {
+                let half: BigInt = &self.one / 2;
+                let whole = if x.is_negative() {
+                    -((-x + half) / &self.one)
+                } else {
+                    (x + half) / &self.one
+                };
+                Some(whole * &self.one)
+            }
+            ("min", _) => args.iter().min().cloned(),
+            ("max", _) => args.iter().max().cloned(),
+            ("sqrt", [x]) => self.sqrt(x),
+            ("exp", [x]) => self.exp(x),
+            ("ln", [x]) => self.ln(x),
+            ("log", [base, x]) => self.div(&self.ln(x)?, &self.ln(base)?),
+            ("sin", [x]) => Some(self.sin_cos(&rad(x)).0),
+            ("cos", [x]) => Some(self.sin_cos(&rad(x)).1),
+            ("tan", [x]) => {
+                let (sin, cos) = self.sin_cos(&rad(x));
+                self.div(&sin, &cos)
+            }
+            ("atan", [x]) => self.to_unit(self.atan(x), unit),
+            // an angle in a unit of its own, see angle.rs
+            ("deg", [x]) => self.to_unit(self.to_radians(x, AngleUnit::Degree), unit),
+            ("rad", [x]) => self.to_unit(x.clone(), unit),
+            ("grad", [x]) => self.to_unit(self.mul(x, &self.pi) / 200, unit),
+            ("turn", [x]) => self.to_unit(self.to_radians(x, AngleUnit::Turn), unit),
+            ("asin", [x]) | ("acos", [x]) if x.abs() <= self.one => {
+                let asin = if x.abs() == self.one {
+                    &self.pi / 2 * x.signum()
+                } else {
+                    let root = self.sqrt(&(&self.one - self.mul(x, x)))?;
+                    self.atan(&self.div(x, &root)?)
+                };
+                let angle = if name == "asin" {
+                    asin
+                } else {
+                    &self.pi / 2 - asin
+                };
+                self.to_unit(angle, unit)
+            }
+            ("sinh", [x]) | ("cosh", [x]) | ("tanh", [x]) => {
+                let (up, down) = (self.exp(&rad(x))?, self.exp(&-rad(x))?);
+                match name {
+                    "sinh" => Some((up - down) / 2),
+                    "cosh" => Some((up + down) / 2),
+                    _ => self.div(&(&up - &down), &(up + down)),
+                }
+            }
+            ("asinh", [x]) => {
+                let root = self.sqrt(&(self.mul(x, x) + &self.one))?;
+                self.to_unit(self.ln(&(x + root))?, unit)
+            }
+            ("acosh", [x]) if *x >= self.one => {
+                let root = self.sqrt(&(self.mul(x, x) - &self.one))?;
+                self.to_unit(self.ln(&(x + root))?, unit)
+            }
+            ("atanh", [x]) if x.abs() < self.one => {
+                let ratio = self.div(&(&self.one + x), &(&self.one - x))?;
+                self.to_unit(self.ln(&ratio)? / 2, unit)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{func, testing};
+
+    fn precise(query: &str, digits: u32) -> Option<String> {
+        let context = testing::context();
+        eval(&func::parse(query, &context)?, &context, digits)
+    }
+
+    #[test]
+    fn irrationals_to_many_digits() {
+        assert_eq!(
+            precise("sqrt(2)", 50).as_deref(),
+            Some("1.41421356237309504880168872420969807856967187537695")
+        );
+        assert_eq!(
+            precise("pi", 40).as_deref(),
+            Some("3.1415926535897932384626433832795028841972")
+        );
+        assert_eq!(
+            precise("e", 30).as_deref(),
+            Some("2.718281828459045235360287471353")
+        );
+        assert_eq!(
+            precise("ln(2)", 30).as_deref(),
+            Some("0.693147180559945309417232121458")
+        );
+        assert_eq!(precise("atan(1)*4 - pi", 50).as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn exact_decimals_and_trimming() {
+        assert_eq!(precise("0.1+0.2", 30).as_deref(), Some("0.3"));
+        assert_eq!(precise("1/3", 10).as_deref(), Some("0.3333333333"));
+        assert_eq!(precise("-2/3", 5).as_deref(), Some("-0.66667"));
+        assert_eq!(
+            precise("2^100", 5).as_deref(),
+            Some("1267650600228229401496703205376")
+        );
+        assert_eq!(precise("sin(pi)", 40).as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn sums_and_user_functions() {
+        let mut context = testing::context();
+        num_parser::eval_with_mutable_context("f(x) = x^2 + 1", &mut context).unwrap();
+        let expr = func::parse("sum(1/f(k), k, 0, 3)", &context).unwrap();
+        // 1 + 1/2 + 1/5 + 1/10
+        assert_eq!(eval(&expr, &context, 20).as_deref(), Some("1.8"));
+    }
+
+    #[test]
+    fn gives_up_where_it_should() {
+        assert_eq!(precise("1/0", 10), None);
+        assert_eq!(precise("sqrt(-1)", 10), None);
+        assert_eq!(precise("ln(0)", 10), None);
+        assert_eq!(precise("exp(100000)", 10), None);
+        assert_eq!(precise("asin(2)", 10), None);
+    }
+}
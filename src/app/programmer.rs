@@ -0,0 +1,360 @@
+// programmer mode - hex/binary/octal literals, bitwise operations and multi-base display of integer results
+// num_parser only understands decimal numbers and has no bitwise operators, so there are three parts to this:
+// - preprocess() rewrites queries before num_parser sees them, 0xff becomes 255 and a & b becomes and((a),(b))
+// - register() adds and(), or(), xor(), not(), shl() and shr() as built-in functions working on 64 bit integers
+// - bases() formats an integer result in dec/hex/oct/bin, wrapped to the chosen word size
+use crate::app::{func, lib::DisplaySettings};
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    Context, ErrorType, EvalResult, Expression, Value,
+};
+
+pub fn register() {
+    builtin::add_built_in_function(Function::new("and", and, Arguments::Const(2)));
+    builtin::add_built_in_function(Function::new("or", or, Arguments::Const(2)));
+    builtin::add_built_in_function(Function::new("xor", xor, Arguments::Const(2)));
+    builtin::add_built_in_function(Function::new("not", not, Arguments::Const(1)));
+    builtin::add_built_in_function(Function::new("shl", shl, Arguments::Const(2)));
+    builtin::add_built_in_function(Function::new("shr", shr, Arguments::Const(2)));
+}
+
+// the &Vec signatures are fixed by num_parser's Function type
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn and(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (a, b) = int_pair(arguments, context, depth)?;
+    Ok(Value::Int(a & b))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn or(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (a, b) = int_pair(arguments, context, depth)?;
+    Ok(Value::Int(a | b))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn xor(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (a, b) = int_pair(arguments, context, depth)?;
+    Ok(Value::Int(a ^ b))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn not(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    Ok(Value::Int(
+        !arguments[0].eval(context, None, depth)?.as_int()?,
+    ))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn shl(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (a, n) = int_pair(arguments, context, depth)?;
+    Ok(Value::Int(a.wrapping_shl(shift_amount("shl", n)?)))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn shr(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    // logical shift, so masks don't get smeared with sign bits
+    let (a, n) = int_pair(arguments, context, depth)?;
+    Ok(Value::Int(((a as u64) >> shift_amount("shr", n)?) as i64))
+}
+fn int_pair(
+    arguments: &[Box<Expression>],
+    context: &Context,
+    depth: u32,
+) -> EvalResult<(i64, i64)> {
+    Ok((
+        arguments[0].eval(context, None, depth)?.as_int()?,
+        arguments[1].eval(context, None, depth)?.as_int()?,
+    ))
+}
+fn shift_amount(name: &'static str, n: i64) -> EvalResult<u32> {
+    match n {
+        0..=63 => Ok(n as u32),
+        _ => Err(ErrorType::ErrorDuring {
+            operation_name: name,
+            error: Box::new(ErrorType::InternalError {
+                message: format!("can't shift a 64 bit integer by {} places", n),
+            }),
+        }),
+    }
+}
+
+pub fn preprocess(query: &str) -> String {
+    // for declarations like m = 0xff & x only the right hand side gets rewritten
+    let chars: Vec<char> = query.chars().collect();
+    match func::assignment(query) {
+        Some(i) => {
+            let (name, body): (String, String) =
+                (chars[..i].iter().collect(), chars[i + 1..].iter().collect());
+            format!("{}={}", name, bitwise(&literals(&body)))
+        }
+        None => bitwise(&literals(query)),
+    }
+}
+
+// Swaps any 0x.., 0b.. and 0o.. literals for their decimal value
+// Values over i64::MAX like 0xffffffffffffffff wrap round to negatives, same as a u64 -> i64 cast
+fn literals(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_word = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '.');
+        let radix = match chars.get(i + 1) {
+            Some('x') | Some('X') => 16,
+            Some('b') | Some('B') => 2,
+            Some('o') | Some('O') => 8,
+            _ => 0,
+        };
+        if starts_word && chars[i] == '0' && radix != 0 {
+            let digits: String = chars[i + 2..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .collect();
+            if let Ok(n) = u64::from_str_radix(&digits.replace('_', ""), radix) {
+                match n as i64 {
+                    n if n < 0 => out.push_str(&format!("({})", n)),
+                    n => out.push_str(&n.to_string()),
+                }
+                i += 2 + digits.chars().count();
+                continue;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+// Rewrites infix bitwise operators into function calls, a | b & c << 2 becomes or((a),(and((b),(shl((c),(2))))))
+// and ~a becomes not(a). Precedence is the same as C, lowest first: | then & then the shifts.
+// So like in C, a & b == 0 means a & (b == 0) - brackets are your friend here
+fn bitwise(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    // find the operators at the top bracket level, noting where the commas are as we go
+    // ops are (position, length, precedence, function name)
+    let mut depth = 0;
+    let mut commas = vec![];
+    let mut ops: Vec<(usize, usize, u8, &str)> = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        let prev = if i > 0 { Some(chars[i - 1]) } else { None };
+        match chars[i] {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => commas.push(i),
+            '|' if depth == 0 && next != Some('|') && prev != Some('|') => {
+                ops.push((i, 1, 0, "or"))
+            }
+            '&' if depth == 0 && next != Some('&') && prev != Some('&') => {
+                ops.push((i, 1, 1, "and"))
+            }
+            '<' if depth == 0 && next == Some('<') => {
+                ops.push((i, 2, 2, "shl"));
+                i += 1;
+            }
+            '>' if depth == 0 && next == Some('>') => {
+                ops.push((i, 2, 2, "shr"));
+                i += 1;
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    let slice = |from: usize, to: usize| chars[from..to].iter().collect::<String>();
+
+    if !commas.is_empty() {
+        let mut pieces = vec![];
+        let mut start = 0;
+        for comma in commas.into_iter().chain(std::iter::once(chars.len())) {
+            pieces.push(bitwise(&slice(start, comma)));
+            start = comma + 1;
+        }
+        return pieces.join(",");
+    }
+    // lowest precedence operator splits first, and the rightmost one since they're all left associative
+    let lowest = ops.iter().map(|op| op.2).min();
+    if let Some(&(pos, len, _, name)) = ops.iter().rev().find(|op| Some(op.2) == lowest) {
+        return format!(
+            "{}(({}),({}))",
+            name,
+            bitwise(&slice(0, pos)),
+            bitwise(&slice(pos + len, chars.len()))
+        );
+    }
+
+    // no operators on this level, so just look inside each bracket and handle any ~
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '(' => match closing_bracket(&chars, i) {
+                Some(end) => {
+                    out.push_str(&format!("({})", bitwise(&slice(i + 1, end))));
+                    i = end + 1;
+                }
+                None => {
+                    // unbalanced, leave it for num_parser to complain about
+                    out.push_str(&slice(i, chars.len()));
+                    break;
+                }
+            },
+            '~' => {
+                // the operand is a bracket, or a number/name possibly followed by a bracket for function calls
+                let start = i + 1;
+                let mut end = start;
+                while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '.') {
+                    end += 1;
+                }
+                if end < chars.len() && chars[end] == '(' {
+                    end = closing_bracket(&chars, end).map_or(chars.len(), |e| e + 1);
+                }
+                out.push_str(&format!("not({})", bitwise(&slice(start, end))));
+                i = end;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+fn closing_bracket(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+// Shows an integer in all four bases. The bits are wrapped to the word size first,
+// so with an 8 bit word -1 shows as 0xff, and as 255 or -1 in decimal depending on signedness
+pub fn bases(val: &Value, settings: &DisplaySettings) -> Option<String> {
+    let n = match val {
+        Value::Int(n) => *n,
+        _ => return None,
+    };
+    let bits = settings.word_size;
+    let mask = if bits >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << bits) - 1
+    };
+    let raw = (n as u64) & mask;
+    let dec = if settings.signed && bits < 64 && raw >> (bits - 1) == 1 {
+        (raw as i128 - (1i128 << bits)).to_string()
+    } else if settings.signed {
+        (raw as i64).to_string()
+    } else {
+        raw.to_string()
+    };
+    let overflow = if dec != n.to_string() {
+        format!("  ({} doesn't fit in {})", n, settings.word_name())
+    } else {
+        "".to_string()
+    };
+    Some(format!(
+        "dec {}{}\nhex 0x{:x}\noct 0o{:o}\nbin 0b{}",
+        dec,
+        overflow,
+        raw,
+        raw,
+        group_bits(&format!("{:b}", raw))
+    ))
+}
+fn group_bits(bin: &str) -> String {
+    // nibbles separated by underscores, counting from the right
+    let chars: Vec<char> = bin.chars().collect();
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if i > 0 && (chars.len() - i).is_multiple_of(4) {
+            out.push('_');
+        }
+        out.push(*c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals_become_decimal() {
+        assert_eq!(literals("0xff + 0b1010 + 0o17"), "255 + 10 + 15");
+        assert_eq!(literals("0XFF"), "255");
+        assert_eq!(literals("0b1111_0000"), "240");
+        // past i64::MAX wraps round like a u64 -> i64 cast, in brackets so it's still one value
+        assert_eq!(literals("0xffffffffffffffff"), "(-1)");
+    }
+
+    #[test]
+    fn literals_leave_names_and_decimals_alone() {
+        assert_eq!(literals("x0b1"), "x0b1");
+        assert_eq!(literals("1.0b"), "1.0b");
+        // not a valid binary number, so num_parser gets to complain about it
+        assert_eq!(literals("0b12"), "0b12");
+    }
+
+    #[test]
+    fn precedence_is_the_same_as_c() {
+        assert_eq!(bitwise("a|b&c<<2"), "or((a),(and((b),(shl((c),(2))))))");
+        // all left associative, so the rightmost operator is the outermost call
+        assert_eq!(bitwise("a<<1>>2"), "shr((shl((a),(1))),(2))");
+    }
+
+    #[test]
+    fn not_and_brackets() {
+        assert_eq!(bitwise("~a"), "not(a)");
+        assert_eq!(bitwise("~(a|b)"), "not((or((a),(b))))");
+        assert_eq!(bitwise("max(a&b, 3)"), "max(and((a),(b)), 3)");
+    }
+
+    #[test]
+    fn logical_operators_are_left_alone() {
+        assert_eq!(bitwise("a && b || c"), "a && b || c");
+    }
+
+    #[test]
+    fn only_the_declared_value_is_rewritten() {
+        assert_eq!(preprocess("m=0xff&x"), "m=and((255),(x))");
+    }
+
+    #[test]
+    fn bases_wrap_to_the_word_size() {
+        let settings = DisplaySettings {
+            word_size: 8,
+            ..DisplaySettings::default()
+        };
+        assert_eq!(
+            bases(&Value::Int(-1), &settings).unwrap(),
+            "dec -1\nhex 0xff\noct 0o377\nbin 0b1111_1111"
+        );
+        let unsigned = DisplaySettings {
+            signed: false,
+            ..settings
+        };
+        assert!(bases(&Value::Int(-1), &unsigned)
+            .unwrap()
+            .starts_with("dec 255  (-1 doesn't fit in u8)"));
+        assert_eq!(bases(&Value::Float(1.5), &settings), None);
+    }
+
+    #[test]
+    fn bitwise_functions() {
+        use crate::app::testing::eval;
+        let eval = |q: &str| eval(&preprocess(q));
+        assert_eq!(eval("0b1100 & 0b1010").unwrap(), Value::Int(8));
+        assert_eq!(eval("0b1100 | 0b1010").unwrap(), Value::Int(14));
+        assert_eq!(eval("xor(12, 10)").unwrap(), Value::Int(6));
+        assert_eq!(eval("~0").unwrap(), Value::Int(-1));
+        assert_eq!(eval("1 << 4 >> 2").unwrap(), Value::Int(4));
+        assert!(eval("1 << 64").is_err());
+        assert!(eval("1.5 & 1").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+// sum(expr, k, a, b) and prod(expr, k, a, b), added to num_parser as extra built-in functions
+// num_parser hands built-ins the raw argument expressions rather than values, so we can re-evaluate expr
+// once for each integer k from a to b, binding k as a temporary variable on a copy of the context
+// e.g. sum(k^2, k, 1, 10) = 385, prod(k, k, 1, 5) = 120, and f(n) = sum(1/k, k, 1, n) works as a user function
+// Given just one argument they add up or multiply a list instead, sum([1,2,3]) = 6 (see stats.rs)
+use crate::app::{func, worker};
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    Context, ErrorType, EvalResult, Expression, Value,
+};
+
+// Every term clones the context and re-evaluates the whole expression, so a huge range would freeze the UI
+// Anything with more terms than this gets refused rather than attempted
+pub const MAX_TERMS: i64 = 100_000;
+
+pub fn register() {
+    // Dynamic since they take either one argument or four, iterate() checks which
+    builtin::add_built_in_function(Function::new("sum", sum, Arguments::Dynamic));
+    builtin::add_built_in_function(Function::new("prod", prod, Arguments::Dynamic));
+}
+
+// the &Vec signatures are fixed by num_parser's Function type
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn sum(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    iterate("sum", arguments, context, depth, Value::Int(0), Value::add)
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn prod(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    iterate("prod", arguments, context, depth, Value::Int(1), Value::mul)
+}
+
+fn iterate(
+    name: &'static str,
+    arguments: &[Box<Expression>],
+    context: &Context,
+    depth: u32,
+    empty: Value,
+    combine: fn(Value, Value) -> EvalResult<Value>,
+) -> EvalResult<Value> {
+    let during = |error| ErrorType::ErrorDuring {
+        operation_name: name,
+        error: Box::new(error),
+    };
+    match arguments.len() {
+        1 => {
+            let list = arguments[0].eval(context, None, depth)?.as_vector();
+            return list.into_iter().try_fold(empty, combine);
+        }
+        4 => (),
+        given => {
+            return Err(ErrorType::WrongFunctionArgumentsAmount {
+                func_name: name.to_string(),
+                expected: 4,
+                given: given as u8,
+            })
+        }
+    }
+    // the iteration variable has to be a plain name, and can't shadow a constant like e or i
+    // since num_parser always looks those up before any user variable
+    let var = match &*arguments[1] {
+        Expression::Var(v) if builtin::reserved_keywords().contains(&&v[..]) => {
+            return Err(during(ErrorType::ReservedVarName {
+                var_name: v.clone(),
+            }))
+        }
+        Expression::Var(v) => v.clone(),
+        other => {
+            return Err(during(ErrorType::InternalError {
+                message: format!(
+                    "`{}` is not a valid iteration variable",
+                    func::parser(other)
+                ),
+            }))
+        }
+    };
+    // depth gets passed down everywhere, so recursion inside the summand still hits the context's DepthLimit
+    let lower = arguments[2].eval(context, None, depth)?.as_int()?;
+    let upper = arguments[3].eval(context, None, depth)?.as_int()?;
+    let terms = upper.saturating_sub(lower).saturating_add(1);
+    if terms > MAX_TERMS {
+        return Err(during(ErrorType::InternalError {
+            message: format!(
+                "a range of {} terms is over the limit of {}",
+                terms, MAX_TERMS
+            ),
+        }));
+    }
+
+    let mut scope = context.clone();
+    let mut total = empty;
+    for k in lower..=upper {
+        worker::check(name)?;
+        scope.add_variable(var.clone(), Box::new(Expression::Literal(Value::Int(k))));
+        total = combine(total, arguments[0].eval(&scope, None, depth)?)?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::testing::{close, context, eval};
+    use num_parser::Value;
+
+    #[test]
+    fn header_examples() {
+        // num_parser's ^ always gives a float
+        assert!(close("sum(k^2, k, 1, 10)", 385.0));
+        assert_eq!(eval("sum(k*k, k, 1, 10)").unwrap(), Value::Int(385));
+        assert_eq!(eval("prod(k, k, 1, 5)").unwrap(), Value::Int(120));
+        let mut context = context();
+        num_parser::eval_with_mutable_context("f(n) = sum(1/k, k, 1, n)", &mut context).unwrap();
+        let h = num_parser::eval_with_static_context("f(4)", &context).unwrap();
+        assert!((h.as_float().unwrap() - 25.0 / 12.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn empty_ranges() {
+        assert_eq!(eval("sum(k, k, 5, 1)").unwrap(), Value::Int(0));
+        assert_eq!(eval("prod(k, k, 5, 1)").unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn the_variable_is_only_bound_inside() {
+        assert_eq!(eval("sum(k, k, -2, 2)").unwrap(), Value::Int(0));
+        assert!(eval("sum(k, k, 1, 3) + k").is_err());
+    }
+
+    #[test]
+    fn bad_arguments() {
+        // e is a constant, num_parser would always look it up first
+        assert!(eval("sum(e, e, 1, 3)").is_err());
+        assert!(eval("sum(k, 2, 1, 3)").is_err());
+        assert!(eval("sum(k, k, 1, 1000000)").is_err());
+    }
+}
@@ -0,0 +1,440 @@
+// algebraic simplification of the expressions stored in our num_parser context
+// simplify() keeps running a single rewriting pass over the tree until nothing changes any more
+// each pass does:
+// - constant folding, but only when it's exact - 2*3 becomes 6 but 1/3 stays as it is
+// - identities like x*1, x+0, x^1, x^0, --x
+// - like terms in sums, 2x + x - y + 3y becomes 3x+2y
+// - powers in products, x*x^2/x becomes x^2, and (x^2)^3 becomes x^6
+// if a pass can't build a node it needs (see binary()) the expression comes back exactly as it was given
+use num_parser::{Context, Expression, Expression::*, Value};
+use std::sync::OnceLock;
+
+// A few passes is always plenty, this just stops us looping forever if two rules ever fight each other
+const MAX_PASSES: usize = 32;
+
+pub fn simplify(expr: &Expression) -> Expression {
+    let mut current = expr.clone();
+    for _ in 0..MAX_PASSES {
+        let Some(next) = pass(&current) else {
+            return expr.clone();
+        };
+        if next == current {
+            break;
+        }
+        current = next;
+    }
+    current
+}
+
+// num_parser keeps its TokenType private, so we can't just write `Plus` ourselves.
+// Operators get compared through their Display impl ("+", "*" ...) instead,
+// and whenever we need a brand new node we borrow the token out of a parsed template expression
+fn templates() -> &'static Context {
+    static TEMPLATES: OnceLock<Context> = OnceLock::new();
+    TEMPLATES.get_or_init(|| {
+        let mut ctxt = Context::default();
+        for decl in [
+            "p = a+b", "m = a-b", "t = a*b", "d = a/b", "c = a^b", "n = -a",
+        ] {
+            let _ = num_parser::eval_with_mutable_context(decl, &mut ctxt);
+        }
+        ctxt
+    })
+}
+// only the operators there's a template for, anything else gives None rather than losing an operand
+fn binary(lhs: Expression, op: &str, rhs: Expression) -> Option<Expression> {
+    let name = match op {
+        "+" => "p",
+        "-" => "m",
+        "*" => "t",
+        "/" => "d",
+        "^" => "c",
+        _ => return None,
+    };
+    match templates().variables.get(name).map(|b| &**b) {
+        Some(Binary(_, token, _)) => Some(Binary(Box::new(lhs), *token, Box::new(rhs))),
+        _ => None,
+    }
+}
+fn negate(expr: Expression) -> Option<Expression> {
+    match expr {
+        Literal(v) if is_number(&v) => Some(Literal(v.negate().unwrap_or(Value::Int(0)))),
+        Unary(token, inner) if token.to_string() == "-" => Some(*inner),
+        other => match templates().variables.get("n").map(|b| &**b) {
+            Some(Unary(token, _)) => Some(Unary(*token, Box::new(other))),
+            _ => None,
+        },
+    }
+}
+
+fn pass(expr: &Expression) -> Option<Expression> {
+    Some(match expr {
+        Binary(lhs, token, rhs) => {
+            let (lhs, rhs) = (pass(lhs)?, pass(rhs)?);
+            match &*token.to_string() {
+                "+" | "-" => combine_terms(&Binary(Box::new(lhs), *token, Box::new(rhs)))?,
+                "*" | "/" => combine_factors(&Binary(Box::new(lhs), *token, Box::new(rhs)))?,
+                "^" => power(lhs, rhs)?,
+                _ => fold(Binary(Box::new(lhs), *token, Box::new(rhs))),
+            }
+        }
+        Unary(token, inner) => {
+            let inner = pass(inner)?;
+            if token.to_string() == "-" {
+                negate(inner)?
+            } else {
+                Unary(*token, Box::new(inner))
+            }
+        }
+        Func(name, args) => Func(
+            name.clone(),
+            args.iter()
+                .map(|arg| Some(Box::new(pass(arg)?)))
+                .collect::<Option<_>>()?,
+        ),
+        Union(exprs) => Union(
+            exprs
+                .iter()
+                .map(|e| Some(Box::new(pass(e)?)))
+                .collect::<Option<_>>()?,
+        ),
+        Var(_) | Literal(_) => expr.clone(),
+    })
+}
+
+// Two literals either side of an operator get evaluated, as long as we don't lose anything doing so
+fn fold(expr: Expression) -> Expression {
+    if let Binary(lhs, token, rhs) = &expr {
+        if let (Literal(a), Literal(b)) = (&**lhs, &**rhs) {
+            let exact_inputs = matches!(a, Value::Int(_)) && matches!(b, Value::Int(_));
+            if let Some(val) = arith(a, &token.to_string(), b) {
+                if !exact_inputs || matches!(val, Value::Int(_) | Value::Bool(_)) {
+                    return Literal(val);
+                }
+            }
+        }
+    }
+    expr
+}
+
+fn power(base: Expression, exp: Expression) -> Option<Expression> {
+    Some(match (&base, &exp) {
+        (_, Literal(e)) if is_zero(e) => Literal(Value::Int(1)),
+        (_, Literal(e)) if is_one(e) => base,
+        (Literal(b), _) if is_one(b) => base,
+        (Literal(_), Literal(_)) => fold(binary(base, "^", exp)?),
+        // (x^a)^b = x^(ab) is only safe in general for integer b
+        (Binary(inner_base, token, inner_exp), Literal(Value::Int(_)))
+            if token.to_string() == "^" =>
+        {
+            binary(
+                (**inner_base).clone(),
+                "^",
+                pass(&binary((**inner_exp).clone(), "*", exp)?)?,
+            )?
+        }
+        _ => binary(base, "^", exp)?,
+    })
+}
+
+// Flattens a chain of +/- into (coefficient, term) pairs, so 2x - y + 3 gives [(2, x), (-1, y), (3, constant)]
+// then adds up the coefficients of matching terms and rebuilds the sum
+fn combine_terms(expr: &Expression) -> Option<Expression> {
+    fn collect(expr: &Expression, positive: bool, out: &mut Vec<(Value, Option<Expression>)>) {
+        match expr {
+            Binary(lhs, token, rhs) if token.to_string() == "+" => {
+                collect(lhs, positive, out);
+                collect(rhs, positive, out);
+            }
+            Binary(lhs, token, rhs) if token.to_string() == "-" => {
+                collect(lhs, positive, out);
+                collect(rhs, !positive, out);
+            }
+            Unary(token, inner) if token.to_string() == "-" => collect(inner, !positive, out),
+            _ => {
+                let (coef, term) = split_coefficient(expr);
+                let coef = if positive {
+                    coef
+                } else {
+                    coef.negate().unwrap_or(Value::Int(0))
+                };
+                let existing = out.iter_mut().find(|(_, t)| *t == term);
+                match existing.map(|(c, _)| (arith(c, "+", &coef), c)) {
+                    Some((Some(sum), c)) => *c = sum,
+                    _ => out.push((coef, term)),
+                }
+            }
+        }
+    }
+    let mut terms = vec![];
+    collect(expr, true, &mut terms);
+
+    // constants go at the end, so we get x+1 rather than 1+x
+    terms.sort_by_key(|(_, term)| term.is_none());
+    let mut out: Option<Expression> = None;
+    for (coef, term) in terms.into_iter().filter(|(c, _)| !is_zero(c)) {
+        let negative = is_negative(&coef);
+        let magnitude = if negative {
+            coef.negate().unwrap_or(Value::Int(0))
+        } else {
+            coef
+        };
+        let term = match term {
+            None => Literal(magnitude),
+            Some(t) if is_one(&magnitude) => t,
+            Some(t) => binary(Literal(magnitude), "*", t)?,
+        };
+        out = Some(match (out, negative) {
+            (None, false) => term,
+            (None, true) => negate(term)?,
+            (Some(acc), false) => binary(acc, "+", term)?,
+            (Some(acc), true) => binary(acc, "-", term)?,
+        });
+    }
+    Some(out.unwrap_or(Literal(Value::Int(0))))
+}
+fn split_coefficient(expr: &Expression) -> (Value, Option<Expression>) {
+    // combine_factors always puts the number at the front of a product, so just dig down the left hand side
+    match expr {
+        Literal(v) if is_number(v) => (v.clone(), None),
+        Binary(lhs, token, rhs) if token.to_string() == "*" || token.to_string() == "/" => {
+            match (&**rhs, token.to_string() == "*") {
+                (Literal(c), true) if is_number(c) => (c.clone(), Some((**lhs).clone())),
+                _ => match split_coefficient(lhs) {
+                    (c, Some(rest)) => (c, Some(Binary(Box::new(rest), *token, rhs.clone()))),
+                    (c, None) if token.to_string() == "*" => (c, Some((**rhs).clone())),
+                    _ => (Value::Int(1), Some(expr.clone())),
+                },
+            }
+        }
+        _ => (Value::Int(1), Some(expr.clone())),
+    }
+}
+
+// Same idea for products and quotients, 2x * x^2 / (3y) becomes coefficient 2, denominator 3 and factors [(x, 3), (y, -1)]
+fn combine_factors(expr: &Expression) -> Option<Expression> {
+    struct Factors {
+        coef: Value,
+        denom: Value,
+        // (base, exponent) pairs
+        powers: Vec<(Expression, Expression)>,
+    }
+    fn collect(expr: &Expression, inverted: bool, out: &mut Factors) -> Option<()> {
+        match expr {
+            Binary(lhs, token, rhs) if token.to_string() == "*" => {
+                collect(lhs, inverted, out)?;
+                collect(rhs, inverted, out)?;
+            }
+            Binary(lhs, token, rhs) if token.to_string() == "/" => {
+                collect(lhs, inverted, out)?;
+                collect(rhs, !inverted, out)?;
+            }
+            Unary(token, inner) if token.to_string() == "-" => {
+                out.coef = out.coef.clone().negate().unwrap_or(Value::Int(0));
+                collect(inner, inverted, out)?;
+            }
+            Literal(v) if is_number(v) => {
+                let target = if inverted {
+                    &mut out.denom
+                } else {
+                    &mut out.coef
+                };
+                match arith(target, "*", v) {
+                    Some(product) => *target = product,
+                    None => out
+                        .powers
+                        .push((expr.clone(), exponent(Value::Int(1), inverted)?)),
+                }
+            }
+            _ => {
+                let (base, exp) = match expr {
+                    Binary(base, token, exp) if token.to_string() == "^" => {
+                        ((**base).clone(), (**exp).clone())
+                    }
+                    _ => (expr.clone(), Literal(Value::Int(1))),
+                };
+                let exp = if inverted { negate(exp)? } else { exp };
+                match out.powers.iter_mut().find(|(b, _)| *b == base) {
+                    Some((_, total)) => *total = simplify(&binary(total.clone(), "+", exp)?),
+                    None => out.powers.push((base, exp)),
+                }
+            }
+        }
+        Some(())
+    }
+    fn exponent(v: Value, inverted: bool) -> Option<Expression> {
+        if inverted {
+            negate(Literal(v))
+        } else {
+            Some(Literal(v))
+        }
+    }
+    let mut factors = Factors {
+        coef: Value::Int(1),
+        denom: Value::Int(1),
+        powers: vec![],
+    };
+    collect(expr, false, &mut factors)?;
+    if is_zero(&factors.coef) {
+        return Some(Literal(Value::Int(0)));
+    }
+    // Cancel the numeric part down. Integers are kept as an exact fraction, anything else just gets divided out
+    match (&factors.coef, &factors.denom) {
+        (Value::Int(a), Value::Int(b)) => {
+            let g = gcd(*a, *b);
+            let sign = if *b < 0 { -1 } else { 1 };
+            factors.coef = Value::Int(sign * a / g);
+            factors.denom = Value::Int(sign * b / g);
+        }
+        _ => {
+            if let Some(quotient) = arith(&factors.coef, "/", &factors.denom) {
+                factors.coef = quotient;
+                factors.denom = Value::Int(1);
+            }
+        }
+    }
+
+    let negative = is_negative(&factors.coef);
+    let coef = if negative {
+        factors.coef.negate().unwrap_or(Value::Int(0))
+    } else {
+        factors.coef
+    };
+    // sorting the factors means x*y and y*x come out the same, so combine_terms can match them up
+    factors
+        .powers
+        .sort_by_key(|(base, _)| crate::app::func::parser(base));
+    let mut numerator: Vec<Expression> = vec![];
+    let mut denominator: Vec<Expression> = vec![];
+    if !is_one(&coef) {
+        numerator.push(Literal(coef));
+    }
+    if !is_one(&factors.denom) {
+        denominator.push(Literal(factors.denom));
+    }
+    for (base, exp) in factors.powers {
+        match &exp {
+            Literal(e) if is_zero(e) => (),
+            Literal(e) if is_negative(e) => denominator.push(power(
+                base,
+                Literal(e.clone().negate().unwrap_or(Value::Int(1))),
+            )?),
+            Unary(token, inner) if token.to_string() == "-" => {
+                denominator.push(power(base, (**inner).clone())?)
+            }
+            _ => numerator.push(power(base, exp)?),
+        }
+    }
+    let product = |exprs: Vec<Expression>| {
+        let mut exprs = exprs.into_iter();
+        let first = exprs.next().unwrap_or(Literal(Value::Int(1)));
+        exprs.try_fold(first, |acc, e| binary(acc, "*", e))
+    };
+    let out = if denominator.is_empty() {
+        product(numerator)?
+    } else {
+        binary(product(numerator)?, "/", product(denominator)?)?
+    };
+    if negative {
+        negate(out)
+    } else {
+        Some(out)
+    }
+}
+
+fn arith(a: &Value, op: &str, b: &Value) -> Option<Value> {
+    let (a, b) = (a.clone(), b.clone());
+    match op {
+        "+" => a.add(b),
+        "-" => a.sub(b),
+        "*" => a.mul(b),
+        "/" => a.div(b),
+        "^" => a.exponentiation(b),
+        "%" => a.modulo(b),
+        "<" => a.less_than(b),
+        ">" => a.greater_than(b),
+        "<=" => a.less_or_equal_to(b),
+        ">=" => a.greater_or_equal_to(b),
+        "==" => a.equal_to(b),
+        "!=" => a.not_equal_to(b),
+        "&&" => a.logical_and(b),
+        "||" => a.logical_or(b),
+        _ => return None,
+    }
+    .ok()
+}
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+fn is_number(v: &Value) -> bool {
+    matches!(v, Value::Int(_) | Value::Float(_) | Value::Complex(_))
+}
+fn is_zero(v: &Value) -> bool {
+    is_number(v) && *v == Value::Int(0)
+}
+fn is_one(v: &Value) -> bool {
+    is_number(v) && *v == Value::Int(1)
+}
+fn is_negative(v: &Value) -> bool {
+    match v {
+        Value::Int(i) => *i < 0,
+        Value::Float(f) => *f < 0.0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::func;
+
+    // stored the same way a declaration would be, then written back out the way the Simplify option shows it
+    fn simplified(body: &str) -> String {
+        let mut ctxt = Context::default();
+        num_parser::eval_with_mutable_context(&format!("y = {}", body), &mut ctxt).unwrap();
+        func::parser(&simplify(&ctxt.get_var("y").unwrap()))
+    }
+
+    #[test]
+    fn folds_constants_only_when_exact() {
+        assert_eq!(simplified("2*3"), "6");
+        assert_eq!(simplified("1/3"), "1/3");
+    }
+
+    #[test]
+    fn identities() {
+        assert_eq!(simplified("x*1"), "x");
+        assert_eq!(simplified("x+0"), "x");
+        assert_eq!(simplified("x^1"), "x");
+        assert_eq!(simplified("x^0"), "1");
+        // num_parser already reads --x as -x, so a double negative has to be written out
+        assert_eq!(simplified("-(-x)"), "x");
+    }
+
+    #[test]
+    fn like_terms() {
+        assert_eq!(simplified("2x + x - y + 3y"), "3*x+2*y");
+        assert_eq!(simplified("x - x"), "0");
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(simplified("x*x^2/x"), "x^2");
+        assert_eq!(simplified("(x^2)^3"), "x^6");
+    }
+
+    #[test]
+    fn leaves_other_functions_alone() {
+        assert_eq!(simplified("sin(x)*1"), "sin(x)");
+    }
+
+    #[test]
+    fn operators_it_cant_rebuild() {
+        assert_eq!(simplified("x % 3 + 0"), "x%3");
+        assert_eq!(simplified("(x < 2) * 1"), "x<2");
+    }
+}
@@ -0,0 +1,263 @@
+// statistics over lists of values, e.g. data = [1,4,2,8] then mean(data), median(data), stdev(data)...
+// num_parser has vectors already, (1,4,2,8), but a bracket like that passed straight to a function gets spread
+// out into separate arguments, so [..] gets rewritten to list(..) which always stays in one piece
+// Every function here takes lists and/or plain numbers and flattens them, so mean(data, 10) and mean(1,2,3) work too
+// min and max are num_parser's own and sum/prod are in series.rs, they already handle lists
+use crate::app::complex;
+use num_parser::{
+    function::{builtin, Arguments, Function},
+    Context, ErrorType, EvalResult, Expression, Value,
+};
+
+pub fn register() {
+    let functions: [(&'static str, Call, Arguments); 8] = [
+        ("list", list, Arguments::Dynamic),
+        ("mean", mean, Arguments::Dynamic),
+        ("median", median, Arguments::Dynamic),
+        ("var", var, Arguments::Dynamic),
+        ("stdev", stdev, Arguments::Dynamic),
+        ("quantile", quantile, Arguments::Const(2)),
+        ("linreg", linreg, Arguments::Const(2)),
+        ("corr", corr, Arguments::Const(2)),
+    ];
+    for (name, func, args) in functions {
+        builtin::add_built_in_function(Function::new(name, func, args));
+    }
+}
+// the &Vec signatures are fixed by num_parser's Function type
+#[allow(clippy::vec_box)]
+type Call = fn(&Vec<Box<Expression>>, &Context, u32) -> EvalResult<Value>;
+
+// [1,4,2,8] becomes list(1,4,2,8), brackets inside brackets are fine.
+// A bracket straight after a name or another bracket picks out an element instead, m[2,1] is at(m,2,1) (see matrix.rs)
+pub fn preprocess(query: &str) -> String {
+    let mut out: Vec<char> = vec![];
+    for c in query.chars() {
+        match c {
+            '[' => {
+                let before = out.iter().rev().find(|c| **c != ' ');
+                let start = before
+                    .filter(|c| c.is_alphanumeric() || **c == ')')
+                    .and_then(|_| complex::left_operand(&out, out.len()));
+                match start {
+                    Some(start) => {
+                        let operand: String = out.drain(start..).collect();
+                        out.extend(format!("at({},", operand.trim()).chars());
+                    }
+                    None => out.extend("list(".chars()),
+                }
+            }
+            ']' => out.push(')'),
+            c => out.push(c),
+        }
+    }
+    out.into_iter().collect()
+}
+
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn list(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let values = arguments
+        .iter()
+        .map(|arg| arg.eval(context, None, depth))
+        .collect::<EvalResult<Vec<Value>>>()?;
+    Ok(Value::Vector(values))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn mean(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let data = numbers("mean", arguments, context, depth, 1)?;
+    Ok(Value::Float(average(&data)))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn median(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let data = numbers("median", arguments, context, depth, 1)?;
+    Ok(Value::Float(percentile(&data, 0.5)))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn var(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let data = numbers("var", arguments, context, depth, 2)?;
+    Ok(Value::Float(variance(&data)))
+}
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn stdev(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let data = numbers("stdev", arguments, context, depth, 2)?;
+    Ok(Value::Float(variance(&data).sqrt()))
+}
+// quantile(data, 0.25) is the lower quartile, in between two values it interpolates
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn quantile(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let data = numbers("quantile", &arguments[..1], context, depth, 1)?;
+    let q = arguments[1].eval(context, None, depth)?.as_float()?;
+    if !(0.0..=1.0).contains(&q) {
+        return Err(during("quantile", format!("{} isn't between 0 and 1", q)));
+    }
+    Ok(Value::Float(percentile(&data, q)))
+}
+// least squares fit of y = mx + c, giving back [m, c]
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn linreg(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (xs, ys) = pairs("linreg", arguments, context, depth)?;
+    let (mx, my) = (average(&xs), average(&ys));
+    let sxx: f64 = xs.iter().map(|x| (x - mx).powi(2)).sum();
+    let sxy: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+    if sxx == 0.0 {
+        return Err(during(
+            "linreg",
+            "the x values are all the same".to_string(),
+        ));
+    }
+    let slope = sxy / sxx;
+    Ok(Value::Vector(vec![
+        Value::Float(slope),
+        Value::Float(my - slope * mx),
+    ]))
+}
+// Pearson's r for how well linreg fits, -1 to 1
+#[allow(clippy::ptr_arg, clippy::vec_box)]
+fn corr(arguments: &Vec<Box<Expression>>, context: &Context, depth: u32) -> EvalResult<Value> {
+    let (xs, ys) = pairs("corr", arguments, context, depth)?;
+    let (mx, my) = (average(&xs), average(&ys));
+    let sxx: f64 = xs.iter().map(|x| (x - mx).powi(2)).sum();
+    let syy: f64 = ys.iter().map(|y| (y - my).powi(2)).sum();
+    let sxy: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+    if sxx == 0.0 || syy == 0.0 {
+        return Err(during("corr", "one of the lists doesn't vary".to_string()));
+    }
+    Ok(Value::Float(sxy / (sxx * syy).sqrt()))
+}
+
+fn during(name: &'static str, message: String) -> ErrorType {
+    ErrorType::ErrorDuring {
+        operation_name: name,
+        error: Box::new(ErrorType::InternalError { message }),
+    }
+}
+// All the arguments evaluated and flattened into one list of real numbers, sorted since median and quantile need it
+fn numbers(
+    name: &'static str,
+    arguments: &[Box<Expression>],
+    context: &Context,
+    depth: u32,
+    at_least: usize,
+) -> EvalResult<Vec<f64>> {
+    let mut data = vec![];
+    for arg in arguments {
+        for value in arg.eval(context, None, depth)?.as_vector() {
+            data.push(value.as_float()?);
+        }
+    }
+    if data.len() < at_least {
+        return Err(during(
+            name,
+            format!("needs at least {} value(s), got {}", at_least, data.len()),
+        ));
+    }
+    data.sort_by(f64::total_cmp);
+    Ok(data)
+}
+// two lists of the same length, unsorted since they go together
+fn pairs(
+    name: &'static str,
+    arguments: &[Box<Expression>],
+    context: &Context,
+    depth: u32,
+) -> EvalResult<(Vec<f64>, Vec<f64>)> {
+    let mut lists = vec![];
+    for arg in arguments {
+        let list = arg.eval(context, None, depth)?.as_vector();
+        lists.push(
+            list.iter()
+                .map(|v| v.as_float())
+                .collect::<EvalResult<Vec<f64>>>()?,
+        );
+    }
+    let ys = lists.pop().unwrap_or_default();
+    let xs = lists.pop().unwrap_or_default();
+    if xs.len() != ys.len() || xs.len() < 2 {
+        return Err(during(
+            name,
+            format!(
+                "needs two lists of the same length, at least 2, got {} and {}",
+                xs.len(),
+                ys.len()
+            ),
+        ));
+    }
+    Ok((xs, ys))
+}
+pub fn average(data: &[f64]) -> f64 {
+    data.iter().sum::<f64>() / data.len() as f64
+}
+// the sample variance, dividing by n - 1
+fn variance(data: &[f64]) -> f64 {
+    let mean = average(data);
+    data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (data.len() - 1) as f64
+}
+// data has to be sorted
+fn percentile(data: &[f64], q: f64) -> f64 {
+    let at = q * (data.len() - 1) as f64;
+    let (below, above) = (at.floor() as usize, at.ceil() as usize);
+    data[below] + (data[above] - data[below]) * (at - below as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+
+    fn eval(query: &str) -> EvalResult<Value> {
+        testing::eval(&preprocess(query))
+    }
+    fn close(query: &str, expected: f64) -> bool {
+        testing::close(&preprocess(query), expected)
+    }
+
+    #[test]
+    fn brackets_become_lists() {
+        assert_eq!(preprocess("mean([1,4,2,8])"), "mean(list(1,4,2,8))");
+        assert_eq!(preprocess("[[1,2],[3,4]]"), "list(list(1,2),list(3,4))");
+        assert_eq!(preprocess("x = [ 1, 2 ]"), "x = list( 1, 2 )");
+    }
+
+    #[test]
+    fn brackets_after_a_value_pick_an_element() {
+        assert_eq!(preprocess("m[2,1]"), "at(m,2,1)");
+        assert_eq!(preprocess("f(x)[1]"), "at(f(x),1)");
+        assert_eq!(preprocess("2 * [1,2]"), "2 * list(1,2)");
+    }
+
+    #[test]
+    fn statistics() {
+        assert!(close("mean([1,4,2,8])", 3.75));
+        assert!(close("median([1,4,2,8])", 3.0));
+        assert!(close("median(5, 1, 3)", 3.0));
+        assert!(close("var([1,2,3,4])", 5.0 / 3.0));
+        assert!(close("stdev([2,4,4,4,5,5,7,9])", (32.0f64 / 7.0).sqrt()));
+        assert!(close("quantile([1,2,3,4], 0.25)", 1.75));
+        // lists and plain numbers get flattened together
+        assert!(close("mean([1,2], 3, [4,5])", 3.0));
+    }
+
+    #[test]
+    fn sum_and_prod_of_a_list() {
+        assert!(close("sum([1,2,3])", 6.0));
+        assert!(close("prod([2,3,4])", 24.0));
+        assert!(eval("sum(1, 2)").is_err());
+    }
+
+    #[test]
+    fn regression() {
+        let fit = eval("linreg([1,2,3], [3,5,7])").unwrap().as_vector();
+        assert_eq!(fit.len(), 2);
+        assert!((fit[0].as_float().unwrap() - 2.0).abs() < 1e-9);
+        assert!((fit[1].as_float().unwrap() - 1.0).abs() < 1e-9);
+        assert!(close("corr([1,2,3], [3,2,1])", -1.0));
+    }
+
+    #[test]
+    fn not_enough_to_go_on() {
+        assert!(eval("var([1])").is_err());
+        assert!(eval("quantile([1,2], 2)").is_err());
+        assert!(eval("linreg([1,1], [2,3])").is_err());
+        assert!(eval("corr([1,2,3], [1,2])").is_err());
+    }
+}
@@ -0,0 +1,50 @@
+// shared by the unit tests: num_parser with our built-ins registered, working things out unrounded like the app does
+// and an App to press keys in
+use super::App;
+use crossterm::event::{KeyCode, KeyEvent};
+use num_parser::{settings::Rounding, Context, EvalResult, Value};
+use ratatui::layout::Rect;
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+pub fn context() -> Context {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(super::register_builtins);
+    Context {
+        rounding: Rounding::NoRounding,
+        ..Context::default()
+    }
+}
+pub fn eval(query: &str) -> EvalResult<Value> {
+    num_parser::eval_with_static_context(query, &context())
+}
+// for answers that only come out right to within float error
+pub fn close(query: &str, expected: f64) -> bool {
+    eval(query)
+        .and_then(|val| val.as_float())
+        .is_ok_and(|x| (x - expected).abs() <= 1e-9 * expected.abs().max(1.0))
+}
+
+// with a 100x40 screen for the panes to be laid out on
+pub fn app() -> App {
+    context();
+    App {
+        screen: Rect::new(0, 0, 100, 40),
+        ..App::default()
+    }
+}
+pub fn press(app: &mut App, code: KeyCode) {
+    app.handle_key_event(KeyEvent::from(code));
+}
+pub fn type_in(app: &mut App, text: &str) {
+    text.chars().for_each(|c| press(app, KeyCode::Char(c)));
+    press(app, KeyCode::Enter);
+    // and wait for the worker if that started one
+    while app.job.is_some() {
+        app.check_job();
+        thread::sleep(Duration::from_millis(1));
+    }
+    // the run loop does this before every draw
+    app.vars_cache.refresh(&app.ctxt, &app.display);
+}
@@ -0,0 +1,665 @@
+// physical units, e.g. "5 kg * 9.81 m/s^2" gives 49.05 N and "3 m / 2 s in km/h" gives 5.4 km/h
+// num_parser knows nothing about units (and splits "km" into k*m), so queries mentioning a unit get evaluated here instead
+// every quantity is an f64 in SI base units along with the powers of each base dimension, so m/s^2 is [0, 1, -2, 0...]
+// adding or converting between quantities with different dimensions is an error rather than a silent wrong answer
+// anything that isn't a number or a unit - variables, constants, most functions - still gets evaluated by num_parser
+// and has to come out as a plain number
+// A few rules worth knowing:
+// - user variables win over units, so after "s = 4" the s in "3 m/s" is 4 rather than seconds
+// - writing things next to each other binds tighter than * and /, so "3 m / 2 s" means (3 m)/(2 s)
+// - "in" and "to" convert the result, so inches are "inch" and there's no tonne-metre "tm"
+// - constants from the library come with their units. Units with the same name win, but none are left that clash,
+//   which is why Planck's constant is planck rather than h, the hours in km/h
+use crate::app::{
+    constants,
+    format::{self, NumberFormat},
+    lib::DisplaySettings,
+};
+use num_parser::{function::builtin, settings::Rounding, Context, Value};
+
+// powers of kg, m, s, A, K, mol and cd, in that order
+type Dims = [i32; 7];
+const BASE_NAMES: [&str; 7] = ["kg", "m", "s", "A", "K", "mol", "cd"];
+
+const NONE: Dims = [0, 0, 0, 0, 0, 0, 0];
+const MASS: Dims = [1, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dims = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dims = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dims = [0, 0, 0, 1, 0, 0, 0];
+const AREA: Dims = [0, 2, 0, 0, 0, 0, 0];
+const VOLUME: Dims = [0, 3, 0, 0, 0, 0, 0];
+const SPEED: Dims = [0, 1, -1, 0, 0, 0, 0];
+const FORCE: Dims = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dims = [1, -1, -2, 0, 0, 0, 0];
+const ENERGY: Dims = [1, 2, -2, 0, 0, 0, 0];
+const POWER: Dims = [1, 2, -3, 0, 0, 0, 0];
+const CHARGE: Dims = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dims = [1, 2, -3, -1, 0, 0, 0];
+const RESISTANCE: Dims = [1, 2, -3, -2, 0, 0, 0];
+const CAPACITANCE: Dims = [-1, -2, 4, 2, 0, 0, 0];
+const CONDUCTANCE: Dims = [-1, -2, 3, 2, 0, 0, 0];
+const FLUX: Dims = [1, 2, -2, -1, 0, 0, 0];
+const FLUX_DENSITY: Dims = [1, 0, -2, -1, 0, 0, 0];
+const INDUCTANCE: Dims = [1, 2, -2, -2, 0, 0, 0];
+
+// name, size in SI base units, dimensions, and whether it takes prefixes like k or m
+const UNITS: &[(&str, f64, Dims, bool)] = &[
+    // SI base units, with the gram standing in for the kilogram so that prefixes work
+    ("g", 1e-3, MASS, true),
+    ("m", 1.0, LENGTH, true),
+    ("s", 1.0, TIME, true),
+    ("A", 1.0, CURRENT, true),
+    ("K", 1.0, [0, 0, 0, 0, 1, 0, 0], true),
+    ("mol", 1.0, [0, 0, 0, 0, 0, 1, 0], true),
+    ("cd", 1.0, [0, 0, 0, 0, 0, 0, 1], true),
+    // derived SI units
+    ("Hz", 1.0, [0, 0, -1, 0, 0, 0, 0], true),
+    ("N", 1.0, FORCE, true),
+    ("Pa", 1.0, PRESSURE, true),
+    ("J", 1.0, ENERGY, true),
+    ("W", 1.0, POWER, true),
+    ("C", 1.0, CHARGE, true),
+    ("V", 1.0, VOLTAGE, true),
+    ("ohm", 1.0, RESISTANCE, true),
+    ("Ω", 1.0, RESISTANCE, true),
+    ("F", 1.0, CAPACITANCE, true),
+    ("S", 1.0, CONDUCTANCE, true),
+    ("Wb", 1.0, FLUX, true),
+    ("T", 1.0, FLUX_DENSITY, true),
+    ("H", 1.0, INDUCTANCE, true),
+    // everyday units that go with SI
+    ("L", 1e-3, VOLUME, true),
+    ("l", 1e-3, VOLUME, true),
+    ("min", 60.0, TIME, false),
+    ("h", 3600.0, TIME, false),
+    ("day", 86400.0, TIME, false),
+    ("d", 86400.0, TIME, false),
+    ("week", 604800.0, TIME, false),
+    ("wk", 604800.0, TIME, false),
+    ("yr", 31557600.0, TIME, false),
+    ("t", 1000.0, MASS, false),
+    ("ha", 1e4, AREA, false),
+    ("bar", 1e5, PRESSURE, true),
+    ("atm", 101325.0, PRESSURE, false),
+    ("eV", 1.602176634e-19, ENERGY, true),
+    ("Wh", 3600.0, ENERGY, true),
+    ("Ah", 3600.0, CHARGE, true),
+    ("cal", 4.184, ENERGY, true),
+    // imperial/US
+    ("inch", 0.0254, LENGTH, false),
+    ("ft", 0.3048, LENGTH, false),
+    ("yd", 0.9144, LENGTH, false),
+    ("mi", 1609.344, LENGTH, false),
+    ("lb", 0.45359237, MASS, false),
+    ("oz", 0.028349523125, MASS, false),
+    ("gal", 3.785411784e-3, VOLUME, false),
+    ("mph", 0.44704, SPEED, false),
+    ("kn", 1852.0 / 3600.0, SPEED, false),
+    ("psi", 6894.757293168, PRESSURE, false),
+    ("hp", 745.69987158227, POWER, false),
+];
+// "da" has to come before "d"
+const PREFIXES: &[(&str, f64)] = &[
+    ("da", 1e1),
+    ("Y", 1e24),
+    ("Z", 1e21),
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("µ", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+    ("a", 1e-18),
+];
+// results with these dimensions get shown in the named unit rather than as base units
+const NAMED: &[(&str, Dims)] = &[
+    ("N", FORCE),
+    ("Pa", PRESSURE),
+    ("J", ENERGY),
+    ("W", POWER),
+    ("C", CHARGE),
+    ("V", VOLTAGE),
+    ("Ω", RESISTANCE),
+    ("F", CAPACITANCE),
+    ("S", CONDUCTANCE),
+    ("Wb", FLUX),
+    ("T", FLUX_DENSITY),
+    ("H", INDUCTANCE),
+];
+// these know what to do with units, any other function has its arguments handed to num_parser as they are
+const UNIT_FUNCTIONS: &[&str] = &["sqrt", "abs", "min", "max"];
+
+#[derive(Debug, Clone, Copy)]
+struct Quantity {
+    value: f64,
+    dims: Dims,
+}
+impl Quantity {
+    fn number(value: f64) -> Self {
+        Quantity { value, dims: NONE }
+    }
+    fn is_number(&self) -> bool {
+        self.dims == NONE
+    }
+    fn times(self, other: Quantity, sign: i32) -> Quantity {
+        let mut dims = self.dims;
+        for (d, o) in dims.iter_mut().zip(other.dims) {
+            *d += sign * o;
+        }
+        let value = if sign > 0 {
+            self.value * other.value
+        } else {
+            self.value / other.value
+        };
+        Quantity { value, dims }
+    }
+    fn power(self, exponent: f64) -> Result<Quantity, String> {
+        // m^2 is fine, and so is (m^2)^0.5, but m^0.5 has no meaning
+        let mut dims = NONE;
+        for (d, s) in dims.iter_mut().zip(self.dims) {
+            let scaled = s as f64 * exponent;
+            if scaled.fract() != 0.0 {
+                return Err(format!(
+                    "Can't raise {} to the power of {}",
+                    dims_name(&self.dims),
+                    exponent
+                ));
+            }
+            *d = scaled as i32;
+        }
+        Ok(Quantity {
+            value: self.value.powf(exponent),
+            dims,
+        })
+    }
+}
+
+fn lookup(name: &str) -> Option<(f64, Dims)> {
+    // whole names first so that min is minutes and not milli-inches, then prefixed ones
+    if let Some((_, size, dims, _)) = UNITS.iter().find(|u| u.0 == name) {
+        return Some((*size, *dims));
+    }
+    PREFIXES.iter().find_map(|(prefix, scale)| {
+        let rest = name.strip_prefix(prefix)?;
+        let (_, size, dims, _) = UNITS.iter().find(|u| u.0 == rest && u.3)?;
+        Some((scale * size, *dims))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+// Tokens along with where they start in the query, so bits of it can be handed to num_parser as typed
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<(usize, char)> = query.char_indices().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().map(|c| c.1).collect();
+            Token::Num(
+                text.parse()
+                    .map_err(|_| format!("'{}' isn't a number", text))?,
+            )
+        } else if c.is_alphabetic() {
+            while i < chars.len() && chars[i].1.is_alphabetic() {
+                i += 1;
+            }
+            Token::Ident(chars[start..i].iter().map(|c| c.1).collect())
+        } else {
+            i += 1;
+            match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                '+' | '-' | '*' | '/' | '^' => Token::Op(c),
+                _ => return Err(format!("'{}' can't be used with units", c)),
+            }
+        };
+        tokens.push((at, token));
+    }
+    Ok(tokens)
+}
+
+// None if the query has nothing to do with units, so num_parser should handle it as normal
+// otherwise the result as it should be displayed, or an error message
+pub fn eval(
+    query: &str,
+    context: &Context,
+    display: &DisplaySettings,
+) -> Option<Result<String, String>> {
+    let constants = display.constants;
+    // declarations and comparisons are left to num_parser
+    if query.contains('=') || query.contains('<') || query.contains('>') {
+        return None;
+    }
+    let tokens = tokenize(query).ok()?;
+    let mentions_unit = tokens
+        .iter()
+        .enumerate()
+        .any(|(i, (_, token))| match token {
+            Token::Ident(name) => {
+                tokens.get(i + 1).map(|t| &t.1) != Some(&Token::Open)
+                    && is_unit(name, context, constants)
+            }
+            _ => false,
+        });
+    if !mentions_unit {
+        return None;
+    }
+    // everything gets worked out unrounded, and only rounded for display at the end
+    let mut raw = context.clone();
+    raw.rounding = Rounding::NoRounding;
+    let shown = |x: f64| format_number(x, context.rounding, display.format);
+    Some(convert(query, &tokens, &raw, constants, shown))
+}
+fn is_unit(name: &str, context: &Context, constants: bool) -> bool {
+    // library constants are variables too. Units with the exact same name take priority over them,
+    // but prefixed ones don't, otherwise hbar would be a hectobar
+    if constants && constants::find(name).is_some() {
+        return UNITS.iter().any(|u| u.0 == name);
+    }
+    context.get_var(name).is_none()
+        && builtin::get_built_in_const(name).is_none()
+        && lookup(name).is_some()
+}
+
+fn convert(
+    query: &str,
+    tokens: &[(usize, Token)],
+    context: &Context,
+    constants: bool,
+    round: impl Fn(f64) -> String,
+) -> Result<String, String> {
+    // everything after an "in" or "to" is the unit to show the answer in
+    let split = tokens
+        .iter()
+        .position(|(_, t)| matches!(t, Token::Ident(name) if name == "in" || name == "to"));
+    let (tokens, target) = match split {
+        Some(i) => (&tokens[..i], Some(&tokens[i + 1..])),
+        None => (tokens, None),
+    };
+    let result = parse_all(query, tokens, context, constants)?;
+    match target {
+        None if result.is_number() => Ok(round(result.value)),
+        None => {
+            let (name, _) = NAMED
+                .iter()
+                .find(|(_, dims)| *dims == result.dims)
+                .copied()
+                .unwrap_or(("", NONE));
+            let name = match name {
+                "" => dims_name(&result.dims),
+                named => named.to_string(),
+            };
+            Ok(format!("{} {}", round(result.value), name))
+        }
+        Some([]) => Err("Nothing to convert to".to_string()),
+        Some(target) => {
+            let unit = parse_all(query, target, context, constants)?;
+            let text = query[target[0].0..].trim();
+            if unit.dims != result.dims {
+                return Err(format!(
+                    "Can't convert {} to {}",
+                    dims_name(&result.dims),
+                    text
+                ));
+            }
+            Ok(format!("{} {}", round(result.value / unit.value), text))
+        }
+    }
+}
+
+fn parse_all(
+    query: &str,
+    tokens: &[(usize, Token)],
+    context: &Context,
+    constants: bool,
+) -> Result<Quantity, String> {
+    let mut parser = Parser {
+        query,
+        tokens,
+        pos: 0,
+        context,
+        constants,
+    };
+    let result = parser.sum()?;
+    match tokens.get(parser.pos) {
+        None => Ok(result),
+        Some((at, _)) => Err(format!("Didn't expect '{}'", &query[*at..])),
+    }
+}
+
+// Recursive descent, lowest precedence first:
+// sum: a + b - c, product: a * b / c, juxtaposed: a b c, unary: -a, power: a^b, and then atom
+struct Parser<'a> {
+    query: &'a str,
+    tokens: &'a [(usize, Token)],
+    pos: usize,
+    context: &'a Context,
+    constants: bool,
+}
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.1)
+    }
+    fn next(&mut self) -> Option<Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1).map(|t| t.1.clone())
+    }
+    // the query text from token `from` up to but not including the current one
+    fn text_since(&self, from: usize) -> &str {
+        let start = self.tokens[from].0;
+        let end = self.tokens.get(self.pos).map_or(self.query.len(), |t| t.0);
+        &self.query[start..end]
+    }
+    fn sum(&mut self) -> Result<Quantity, String> {
+        let mut total = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let term = self.product()?;
+            if term.dims != total.dims {
+                return Err(format!(
+                    "Can't {} {} and {}",
+                    if op == '+' { "add" } else { "subtract" },
+                    dims_name(&total.dims),
+                    dims_name(&term.dims)
+                ));
+            }
+            total.value += if op == '+' { term.value } else { -term.value };
+        }
+        Ok(total)
+    }
+    fn product(&mut self) -> Result<Quantity, String> {
+        let mut total = self.juxtaposed()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.next();
+            let factor = self.juxtaposed()?;
+            total = total.times(factor, if op == '*' { 1 } else { -1 });
+        }
+        Ok(total)
+    }
+    fn juxtaposed(&mut self) -> Result<Quantity, String> {
+        let mut total = self.unary()?;
+        while match self.peek() {
+            Some(Token::Ident(name)) => name != "in" && name != "to",
+            Some(Token::Num(_)) | Some(Token::Open) => true,
+            _ => false,
+        } {
+            total = total.times(self.unary()?, 1);
+        }
+        Ok(total)
+    }
+    fn unary(&mut self) -> Result<Quantity, String> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.next();
+            let mut inner = self.unary()?;
+            inner.value = -inner.value;
+            return Ok(inner);
+        }
+        self.power()
+    }
+    fn power(&mut self) -> Result<Quantity, String> {
+        let base = self.atom()?;
+        if self.peek() != Some(&Token::Op('^')) {
+            return Ok(base);
+        }
+        self.next();
+        let exponent = self.unary()?;
+        if !exponent.is_number() {
+            return Err(format!(
+                "Can't raise something to the power of {}",
+                dims_name(&exponent.dims)
+            ));
+        }
+        base.power(exponent.value)
+    }
+    fn atom(&mut self) -> Result<Quantity, String> {
+        let start = self.pos;
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Quantity::number(n)),
+            Some(Token::Open) => {
+                let inner = self.sum()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("Missing a closing bracket".to_string()),
+                }
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::Open) => {
+                self.next();
+                if UNIT_FUNCTIONS.contains(&&name[..]) {
+                    let args = self.arguments()?;
+                    unit_function(&name, &args)
+                } else {
+                    self.skip_brackets()?;
+                    self.number(start)
+                }
+            }
+            Some(Token::Ident(name)) if is_unit(&name, self.context, self.constants) => {
+                let (value, dims) = lookup(&name).unwrap_or((1.0, NONE));
+                Ok(Quantity { value, dims })
+            }
+            Some(Token::Ident(name)) if self.constants && constants::find(&name).is_some() => {
+                let (value, units) = constants::find(&name).unwrap_or((1.0, ""));
+                constant(value, units)
+            }
+            Some(Token::Ident(_)) => self.number(start),
+            Some(_) => Err(format!("Didn't expect '{}'", self.text_since(start))),
+            None => Err("The query ends too soon".to_string()),
+        }
+    }
+    fn arguments(&mut self) -> Result<Vec<Quantity>, String> {
+        let mut args = vec![self.sum()?];
+        loop {
+            match self.next() {
+                Some(Token::Comma) => args.push(self.sum()?),
+                Some(Token::Close) => return Ok(args),
+                _ => return Err("Missing a closing bracket".to_string()),
+            }
+        }
+    }
+    fn skip_brackets(&mut self) -> Result<(), String> {
+        // just after an opening bracket, skips to just after its partner
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Open) => depth += 1,
+                Some(Token::Close) => depth -= 1,
+                Some(_) => (),
+                None => return Err("Missing a closing bracket".to_string()),
+            }
+        }
+        Ok(())
+    }
+    // Hands the text of the tokens since `start` to num_parser, which has to give back a real number
+    fn number(&self, start: usize) -> Result<Quantity, String> {
+        let text = self.text_since(start).trim();
+        match num_parser::eval_with_static_context(text, self.context) {
+            Ok(Value::Int(n)) => Ok(Quantity::number(n as f64)),
+            Ok(Value::Float(x)) => Ok(Quantity::number(x)),
+            Ok(other) => Err(format!("'{}' = {} isn't a real number", text, other)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+fn constant(value: f64, units: &str) -> Result<Quantity, String> {
+    if units.is_empty() {
+        return Ok(Quantity::number(value));
+    }
+    // the units get read on a blank context so that a user's own m or s can't get in the way
+    let tokens = tokenize(units)?;
+    let units = parse_all(units, &tokens, &Context::default(), false)?;
+    Ok(Quantity::number(value).times(units, 1))
+}
+
+fn unit_function(name: &str, args: &[Quantity]) -> Result<Quantity, String> {
+    match (name, args) {
+        ("sqrt", [x]) => x.power(0.5),
+        ("abs", [x]) => Ok(Quantity {
+            value: x.value.abs(),
+            dims: x.dims,
+        }),
+        ("min" | "max", [first, ..]) => {
+            if let Some(other) = args.iter().find(|a| a.dims != first.dims) {
+                return Err(format!(
+                    "Can't compare {} and {}",
+                    dims_name(&first.dims),
+                    dims_name(&other.dims)
+                ));
+            }
+            let pick = if name == "min" { f64::min } else { f64::max };
+            let value = args.iter().map(|a| a.value).fold(first.value, pick);
+            Ok(Quantity {
+                value,
+                dims: first.dims,
+            })
+        }
+        _ => Err(format!("Wrong number of arguments for {}", name)),
+    }
+}
+
+// Base units like kg*m/s^2, or "no units" for a plain number
+fn dims_name(dims: &Dims) -> String {
+    let part = |sign: i32| {
+        BASE_NAMES
+            .iter()
+            .zip(dims)
+            .filter(|(_, p)| **p * sign > 0)
+            .map(|(name, p)| match p.abs() {
+                1 => name.to_string(),
+                n => format!("{}^{}", name, n),
+            })
+            .collect::<Vec<String>>()
+    };
+    let (top, bottom) = (part(1), part(-1));
+    let top = match top.len() {
+        0 => "1".to_string(),
+        _ => top.join("*"),
+    };
+    match bottom.len() {
+        0 if top == "1" => "no units".to_string(),
+        0 => top,
+        1 => format!("{}/{}", top, bottom[0]),
+        _ => format!("{}/({})", top, bottom.join("*")),
+    }
+}
+
+// The same format as everything else, except that in Fixed tiny quantities like 1 eV in J would just round to 0,
+// so they get scientific notation instead
+fn format_number(x: f64, rounding: Rounding, number_format: NumberFormat) -> String {
+    match (rounding, number_format) {
+        (Rounding::Round(places), NumberFormat::Fixed)
+            if x != 0.0 && x.abs() < 0.1f64.powi(places as i32) =>
+        {
+            format::number(x, rounding, NumberFormat::Scientific)
+        }
+        _ => format::number(x, rounding, number_format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(query: &str) -> Option<Result<String, String>> {
+        eval(query, &Context::default(), &DisplaySettings::default())
+    }
+
+    #[test]
+    fn tokens_know_where_they_start() {
+        let tokens = tokenize("3.5 km/h^2").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                (0, Token::Num(3.5)),
+                (4, Token::Ident("km".to_string())),
+                (6, Token::Op('/')),
+                (7, Token::Ident("h".to_string())),
+                (8, Token::Op('^')),
+                (9, Token::Num(2.0)),
+            ]
+        );
+        assert_eq!(
+            tokenize("max(1, 2)").unwrap()[1..],
+            [
+                (3, Token::Open),
+                (4, Token::Num(1.0)),
+                (5, Token::Comma),
+                (7, Token::Num(2.0)),
+                (8, Token::Close)
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_that_cant_be_units() {
+        assert!(tokenize("1.2.3 m").is_err());
+        assert!(tokenize("5 m % 2").is_err());
+    }
+
+    #[test]
+    fn header_examples() {
+        assert_eq!(units("5 kg * 9.81 m/s^2"), Some(Ok("49.05 N".to_string())));
+        assert_eq!(units("3 m / 2 s in km/h"), Some(Ok("5.4 km/h".to_string())));
+    }
+
+    #[test]
+    fn tiny_quantities_dont_round_to_nothing() {
+        assert_eq!(units("1 eV in J"), Some(Ok("1.60217663e-19 J".to_string())));
+    }
+
+    #[test]
+    fn prefixes_and_whole_names() {
+        assert_eq!(units("1 km in m"), Some(Ok("1000 m".to_string())));
+        // min is minutes rather than milli-inches
+        assert_eq!(units("1 min in s"), Some(Ok("60 s".to_string())));
+        assert_eq!(units("2 d in h"), Some(Ok("48 h".to_string())));
+        assert_eq!(units("2 m * 3 m"), Some(Ok("6 m^2".to_string())));
+    }
+
+    #[test]
+    fn mismatched_dimensions_are_errors() {
+        assert!(matches!(units("1 m + 1 s"), Some(Err(_))));
+        assert!(matches!(units("1 m in s"), Some(Err(_))));
+        assert!(matches!(units("1 m in"), Some(Err(_))));
+        assert!(matches!(units("m^0.5"), Some(Err(_))));
+        assert_eq!(units("sqrt(4 m^2)"), Some(Ok("2 m".to_string())));
+    }
+
+    #[test]
+    fn left_to_num_parser() {
+        assert_eq!(units("1 + 2"), None);
+        assert_eq!(units("x = 5 m"), None);
+        assert_eq!(units("sin(2)"), None);
+        // a variable wins over a unit with the same name
+        let mut context = Context::default();
+        num_parser::eval_with_mutable_context("s = 4", &mut context).unwrap();
+        assert_eq!(eval("s * 2", &context, &DisplaySettings::default()), None);
+    }
+}
@@ -0,0 +1,104 @@
+// evaluates num_parser expression trees using some other kind of number than f64
+// exact.rs (big rationals) and precise.rs (big fixed point decimals) both plug into this
+// the walker mirrors how num_parser evaluates things itself - variable lookup order, function parameter scopes,
+// the depth limit and sum()/prod() - and leaves the actual arithmetic to an Engine
+// anything an engine can't handle gives None, and the app just falls back to num_parser's own answer
+use crate::app::{series, worker};
+use num_parser::{
+    function::builtin, settings::DepthLimit, Context, Expression, Expression::*, Value,
+};
+use std::collections::HashMap;
+
+pub trait Engine {
+    type Num: Clone;
+    fn literal(&self, val: &Value) -> Option<Self::Num>;
+    // built-in constants like pi
+    fn constant(&self, name: &str) -> Option<Self::Num>;
+    fn integer(&self, i: i64) -> Self::Num;
+    // None if the number isn't whole
+    fn to_int(&self, n: &Self::Num) -> Option<i64>;
+    fn negate(&self, n: Self::Num) -> Option<Self::Num>;
+    // op is the operator as num_parser displays it, "+", "^" etc.
+    fn binary(&self, op: &str, a: Self::Num, b: Self::Num) -> Option<Self::Num>;
+    // built-in functions, with their arguments already evaluated
+    fn function(&self, name: &str, args: &[Self::Num], context: &Context) -> Option<Self::Num>;
+}
+
+pub fn eval<E: Engine>(engine: &E, expr: &Expression, context: &Context) -> Option<E::Num> {
+    eval_in(engine, expr, context, &HashMap::new(), 0)
+}
+
+fn eval_in<E: Engine>(
+    engine: &E,
+    expr: &Expression,
+    context: &Context,
+    scope: &HashMap<String, E::Num>,
+    depth: u32,
+) -> Option<E::Num> {
+    // gives up if the query's been cancelled (see worker.rs), and has the same depth limit as num_parser,
+    // so recursive user functions can't run away
+    if worker::cancelled() {
+        return None;
+    }
+    if let DepthLimit::Limit(max) = context.depth_limit {
+        if depth >= max {
+            return None;
+        }
+    }
+    let depth = depth + 1;
+    let eval_arg = |e: &Expression| eval_in(engine, e, context, scope, depth);
+    match expr {
+        Literal(val) => engine.literal(val),
+        // lookup order is the same as num_parser - constants, then function parameters, then user variables
+        Var(name) if builtin::get_built_in_const(name).is_some() => engine.constant(name),
+        Var(name) => match scope.get(name) {
+            Some(n) => Some(n.clone()),
+            None => eval_in(engine, &*context.get_var(name)?, context, scope, depth),
+        },
+        Unary(token, inner) if token.to_string() == "-" => engine.negate(eval_arg(inner)?),
+        Binary(lhs, token, rhs) => {
+            engine.binary(&token.to_string(), eval_arg(lhs)?, eval_arg(rhs)?)
+        }
+        Func(name, args) if builtin::get_built_in_function(name).is_some() => {
+            match (&name[..], &args[..]) {
+                ("sum" | "prod", [body, k, a, b]) => {
+                    let Var(k) = &**k else {
+                        return None;
+                    };
+                    let (a, b) = (engine.to_int(&eval_arg(a)?)?, engine.to_int(&eval_arg(b)?)?);
+                    if b.saturating_sub(a) >= series::MAX_TERMS {
+                        return None;
+                    }
+                    let (op, empty) = if name == "sum" { ("+", 0) } else { ("*", 1) };
+                    let mut inner = scope.clone();
+                    let mut total = engine.integer(empty);
+                    for k_val in a..=b {
+                        inner.insert(k.clone(), engine.integer(k_val));
+                        let term = eval_in(engine, body, context, &inner, depth)?;
+                        total = engine.binary(op, total, term)?;
+                    }
+                    Some(total)
+                }
+                _ => {
+                    let args = args
+                        .iter()
+                        .map(|a| eval_arg(a))
+                        .collect::<Option<Vec<_>>>()?;
+                    engine.function(name, &args, context)
+                }
+            }
+        }
+        Func(name, args) => {
+            let (params, body) = context.get_function(name)?;
+            if params.len() != args.len() {
+                return None;
+            }
+            let mut inner = scope.clone();
+            for (param, arg) in params.into_iter().zip(args) {
+                inner.insert(param, eval_arg(arg)?);
+            }
+            eval_in(engine, &body, context, &inner, depth)
+        }
+        _ => None,
+    }
+}
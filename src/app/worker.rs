@@ -0,0 +1,340 @@
+// works out queries on a thread of its own, so something slow like a deeply recursive function doesn't freeze the screen
+// It's the same thread for every query until one is given up on, see below. Each query comes with a copy of the context
+// which gets sent back afterwards along with the answer, since declarations change it.
+// In gradians anything that isn't a declaration is worked out on a converted copy of that, see angle.rs
+// Rust has no way to stop a thread from the outside, so cancelling or running out of time sets the job's flag and stops
+// waiting for it. The loops that can go on for a long time (sum and prod, the exact and precise engines, factor) check
+// the flag and give up, but num_parser's own recursion doesn't, and something like f(n) = f(n-1) + f(n-1) can keep
+// going for hours without ever getting deep. So the thread is let go as well: it ends whenever its job does,
+// and the next query starts a new one
+use crate::app::{
+    angle::{self, Angle},
+    exact, func, matrix, precise,
+};
+use num::BigRational;
+use num_parser::{settings::Rounding, Context, ErrorType, EvalResult, Value};
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SendError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// what the Output pane cycles through while it waits
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+#[derive(Debug, Default)]
+pub struct Worker {
+    // None until the first query, or if the thread's gone
+    queue: Option<Sender<Task>>,
+}
+struct Task {
+    work: Box<dyn FnOnce() + Send>,
+    cancel: Arc<AtomicBool>,
+}
+thread_local! {
+    // the flag of the job the worker thread is on, never set on any other thread
+    static CANCEL: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug)]
+pub struct Job {
+    receiver: Receiver<Done>,
+    cancel: Arc<AtomicBool>,
+    started: Instant,
+    // the query as it was typed, for the history, then after preprocessing,
+    // and the new name given a placeholder if there was one
+    pub input: String,
+    pub query: String,
+    pub placeholder: Option<String>,
+}
+#[derive(Debug)]
+pub struct Done {
+    pub context: Context,
+    pub out: EvalResult<Option<Value>>,
+    pub exact: Option<BigRational>,
+    pub precise: Option<String>,
+}
+pub enum Progress {
+    Working,
+    Finished(Box<Done>),
+    // the thread died without sending anything back, most likely a panic inside num_parser
+    Lost,
+}
+
+// Whether the job being worked out has been cancelled, for the long loops to check
+pub fn cancelled() -> bool {
+    CANCEL.with(|cancel| {
+        cancel
+            .borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    })
+}
+// the same for built-ins, as an error nobody's going to see since the answer gets thrown away
+pub fn check(name: &'static str) -> EvalResult<()> {
+    match cancelled() {
+        true => Err(ErrorType::ErrorDuring {
+            operation_name: name,
+            error: Box::new(ErrorType::InternalError {
+                message: "cancelled".to_string(),
+            }),
+        }),
+        false => Ok(()),
+    }
+}
+
+impl Worker {
+    // exact and precision mode work the answer out again their own ways, which can be slow too, so they happen here as well
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        &mut self,
+        query: String,
+        context: Context,
+        exact_mode: bool,
+        precision: Option<u32>,
+        unit: Angle,
+        input: String,
+        placeholder: Option<String>,
+    ) -> Job {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let job_query = query.clone();
+        let work = move || {
+            let done = work_out(query, context, exact_mode, precision, unit);
+            // nobody's listening any more if it was cancelled, which is fine
+            let _ = sender.send(done);
+        };
+        self.send(Task {
+            work: Box::new(work),
+            cancel: cancel.clone(),
+        });
+        Job {
+            receiver,
+            cancel,
+            started: Instant::now(),
+            input,
+            query: job_query,
+            placeholder,
+        }
+    }
+    // gives up on a job and the thread working it out, see the top of the file
+    pub fn cancel(&mut self, job: &Job) {
+        job.cancel.store(true, Ordering::Relaxed);
+        self.queue = None;
+    }
+    // the first query starts the thread, and so does one after it's gone for whatever reason.
+    // If it can't be started the task gets dropped along with its sender, and the job shows up as Lost
+    fn send(&mut self, task: Task) {
+        let task = match &self.queue {
+            Some(queue) => match queue.send(task) {
+                Ok(()) => return,
+                Err(SendError(task)) => task,
+            },
+            None => task,
+        };
+        self.queue = spawn();
+        if let Some(queue) = &self.queue {
+            let _ = queue.send(task);
+        }
+    }
+}
+fn spawn() -> Option<Sender<Task>> {
+    let (queue, tasks) = mpsc::channel::<Task>();
+    // recursion needs the same big stack as the main thread, see main.rs
+    let worker = move || {
+        for task in tasks {
+            CANCEL.with(|cancel| *cancel.borrow_mut() = Some(task.cancel));
+            // a panic inside num_parser only loses that query, its sender's dropped and the job shows up as Lost
+            let _ = panic::catch_unwind(AssertUnwindSafe(task.work));
+        }
+    };
+    thread::Builder::new()
+        .stack_size(crate::STACK_SIZE)
+        .spawn(worker)
+        .ok()?;
+    Some(queue)
+}
+
+fn work_out(
+    query: String,
+    mut context: Context,
+    exact_mode: bool,
+    precision: Option<u32>,
+    unit: Angle,
+) -> Done {
+    // rounding gets applied when results are displayed (see format.rs), so evaluate without it
+    let rounding = context.rounding;
+    context.rounding = Rounding::NoRounding;
+    let mut converted = (unit == Angle::Gradian && func::assignment(&query).is_none()).then(|| {
+        (
+            angle::in_gradians(&query),
+            angle::context_in(&context, unit),
+        )
+    });
+    let (query, working) = match &mut converted {
+        Some((query, working)) => (&*query, working),
+        None => (&query, &mut context),
+    };
+    let out = matrix::check_query(query, working)
+        .and_then(|()| num_parser::eval_with_mutable_context(query, working));
+    let (mut exact, mut precise) = (None, None);
+    let redo = exact_mode || precision.is_some();
+    if let (Ok(Some(_)), true) = (&out, redo) {
+        if let Some(expr) = func::parse(query, working) {
+            if exact_mode {
+                exact = exact::eval(&expr, working);
+            }
+            if let Some(digits) = precision {
+                precise = precise::eval(&expr, working, digits);
+            }
+        }
+    }
+    context.rounding = rounding;
+    Done {
+        context,
+        out,
+        exact,
+        precise,
+    }
+}
+impl Job {
+    pub fn progress(&self) -> Progress {
+        match self.receiver.try_recv() {
+            Ok(done) => Progress::Finished(Box::new(done)),
+            Err(TryRecvError::Empty) => Progress::Working,
+            Err(TryRecvError::Disconnected) => Progress::Lost,
+        }
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+    // e.g. "⠹ Working it out... 2.3s"
+    pub fn status(&self) -> String {
+        let elapsed = self.elapsed();
+        let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+        format!(
+            "{} Working it out... {:.1}s\n<Esc> or <Ctrl-C> to cancel",
+            frame,
+            elapsed.as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{lib::MAX_DEPTH, testing};
+    use num_parser::settings::DepthLimit;
+
+    fn finish(job: Job) -> Done {
+        loop {
+            match job.progress() {
+                Progress::Working => thread::sleep(Duration::from_millis(1)),
+                Progress::Finished(done) => return *done,
+                Progress::Lost => panic!("the worker thread went away"),
+            }
+        }
+    }
+    fn run(query: &str, context: Context, exact: bool) -> Done {
+        let job = Worker::default().start(
+            query.to_string(),
+            context,
+            exact,
+            Some(20),
+            Angle::Radian,
+            query.to_string(),
+            None,
+        );
+        finish(job)
+    }
+
+    #[test]
+    fn answers_come_back() {
+        let done = run("1/3", testing::context(), true);
+        let answer = done.out.unwrap().unwrap().as_float().unwrap();
+        assert!((answer - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(done.exact.unwrap().to_string(), "1/3");
+        assert_eq!(done.precise.as_deref(), Some("0.33333333333333333333"));
+    }
+
+    #[test]
+    fn declarations_come_back_in_the_context() {
+        let mut context = testing::context();
+        context.rounding = Rounding::Round(3);
+        let done = run("f(x) = 2x", context, false);
+        assert!(matches!(done.out, Ok(None)));
+        assert!(done.context.get_function("f").is_some());
+        // the rounding it was given is put back afterwards
+        assert!(matches!(done.context.rounding, Rounding::Round(3)));
+        assert_eq!(
+            run("f(21)", done.context, false).out.unwrap(),
+            Some(Value::Int(42))
+        );
+    }
+
+    #[test]
+    fn status_counts_up() {
+        let job = Worker::default().start(
+            "1".to_string(),
+            testing::context(),
+            false,
+            None,
+            Angle::Radian,
+            "1".to_string(),
+            None,
+        );
+        assert!(job.status().contains("Working it out... 0."));
+        assert!(job.elapsed() < Duration::from_secs(1));
+        finish(job);
+    }
+
+    #[test]
+    fn the_deepest_recursion_fits_on_the_stack() {
+        let mut deep = testing::context();
+        deep.depth_limit = DepthLimit::Limit(MAX_DEPTH);
+        let f = run("f(n) = branch(n < 1, 0, 1 + f(n - 1))", deep, false);
+        // each call is three levels of depth, so 650 of them is nearly all of MAX_DEPTH
+        let done = run("f(650)", f.context, false);
+        assert_eq!(done.out.unwrap(), Some(Value::Int(650)));
+        // and going past it is an error rather than a stack overflow
+        assert!(run("f(700)", done.context, false).out.is_err());
+    }
+
+    #[test]
+    fn cancelling_leaves_the_next_query_a_free_thread() {
+        let mut worker = Worker::default();
+        let start = |worker: &mut Worker, query: &str, context: Context| {
+            worker.start(
+                query.to_string(),
+                context,
+                false,
+                None,
+                Angle::Radian,
+                query.to_string(),
+                None,
+            )
+        };
+        let mut deep = testing::context();
+        deep.depth_limit = DepthLimit::Limit(MAX_DEPTH);
+        let runaway = run("g(n) = branch(n < 1, 1, g(n - 1) + g(n - 1))", deep, false);
+        // ten billion terms, which checks the flag and stops, and 2^28 calls that don't check anything
+        for (query, context) in [
+            (
+                "sum(sum(j, j, 1, 100000), k, 1, 100000)",
+                testing::context(),
+            ),
+            ("g(28)", runaway.context),
+        ] {
+            let slow = start(&mut worker, query, context);
+            thread::sleep(Duration::from_millis(50));
+            assert!(matches!(slow.progress(), Progress::Working));
+            worker.cancel(&slow);
+            let next = start(&mut worker, "1 + 1", testing::context());
+            let started = Instant::now();
+            assert_eq!(finish(next).out.unwrap(), Some(Value::Int(2)));
+            assert!(started.elapsed() < Duration::from_secs(5));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// Getting the terminal back if the calculator goes down some way other than quitting, a panic or being sent SIGTERM/SIGHUP,
+// otherwise it's left in raw mode on the alternate screen. A crash report goes in a file as well, with whatever was being
+// typed and the history, since those would otherwise be gone.
+// The app's state can't be got at from a panic hook, so the app keeps a copy of the input here with record_input()
+// on every redraw, and of the history with record_history() whenever something's added to it
+use crate::tui;
+use signal_hook::consts::{SIGHUP, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::io;
+use std::panic;
+use std::process;
+use std::sync::{Mutex, OnceLock, TryLockError};
+use std::thread::{self, ThreadId};
+
+// in whatever directory the calculator was started from, like amortization.csv
+const REPORT: &str = "crash-report.txt";
+
+struct Snapshot {
+    input: String,
+    history: Vec<String>,
+}
+static SNAPSHOT: Mutex<Snapshot> = Mutex::new(Snapshot {
+    input: String::new(),
+    history: Vec::new(),
+});
+// the thread the screen belongs to, see install()
+static UI_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+// called from the thread that runs the app, before tui::init
+pub fn install() -> io::Result<()> {
+    let _ = UI_THREAD.set(thread::current().id());
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // a query's thread (see worker.rs) going down is handled by the app, it just says something went wrong.
+        // Printing the panic would only scribble over the screen
+        if Some(&thread::current().id()) != UI_THREAD.get() {
+            return;
+        }
+        let _ = tui::restore();
+        default_hook(info);
+        report(&info.to_string());
+    }));
+    // Ctrl-C comes through as a key in raw mode, so it's only these that can end things from outside
+    let mut signals = Signals::new([SIGTERM, SIGHUP])?;
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            let _ = tui::restore();
+            let name = if signal == SIGHUP {
+                "SIGHUP"
+            } else {
+                "SIGTERM"
+            };
+            report(&format!("stopped by {}", name));
+            process::exit(128 + signal);
+        }
+    });
+    Ok(())
+}
+pub fn record_input(input: String) {
+    let mut snapshot = SNAPSHOT.lock().unwrap_or_else(|err| err.into_inner());
+    snapshot.input = input;
+}
+pub fn record_history(history: Vec<String>) {
+    let mut snapshot = SNAPSHOT.lock().unwrap_or_else(|err| err.into_inner());
+    snapshot.history = history;
+}
+fn report(reason: &str) {
+    match std::fs::write(REPORT, contents(reason)) {
+        Ok(()) => eprintln!("A crash report was saved to {}", REPORT),
+        Err(err) => eprintln!("Couldn't save a crash report to {}: {}", REPORT, err),
+    }
+}
+// the panic might have happened half way through recording, so a poisoned lock is fine, and a held one is skipped over
+fn contents(reason: &str) -> String {
+    let (input, history) = match SNAPSHOT.try_lock() {
+        Ok(snapshot) => (snapshot.input.clone(), snapshot.history.concat()),
+        Err(TryLockError::Poisoned(err)) => {
+            let snapshot = err.into_inner();
+            (snapshot.input.clone(), snapshot.history.concat())
+        }
+        Err(TryLockError::WouldBlock) => ("(unknown)".to_string(), String::new()),
+    };
+    format!(
+        "The calculator went down: {}\n\nInput: {}\n\nHistory, newest first:{}\n",
+        reason, input, history
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_has_the_input_and_history() {
+        record_input("2 * (".to_string());
+        record_history(vec!["\n 1+1 = 2".to_string(), "\n x = 3".to_string()]);
+        assert_eq!(
+            contents("stopped by SIGTERM"),
+            "The calculator went down: stopped by SIGTERM\n\nInput: 2 * (\n\nHistory, newest first:\n 1+1 = 2\n x = 3\n"
+        );
+        // still there after a panic while the snapshot was locked
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = SNAPSHOT.lock();
+            panic!("half way through recording");
+        });
+        assert!(contents("boom").contains("Input: 2 * ("));
+    }
+}
@@ -1,29 +1,39 @@
 // TODO
 // - Enforce a depth limit in parser()?
 // - Remove all the clone()s
-// - PARSER: try the function f(x) = (x-3)^4
-// - - Parentheses aren't displayed
-// - Resizable panes?
 
-use num_parser;
 use std::env;
 use std::io;
+use std::thread;
 mod app;
+mod crash;
 mod tui;
 use app::App;
 
+// every level of a recursive user function takes a good few stack frames in num_parser,
+// the usual 8MB only manages a couple of hundred so everything runs on a thread with more.
+// Recursion can't go past app::lib::MAX_DEPTH, which is what decides how much is enough
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
 fn main() -> io::Result<()> {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)?
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("the calculator panicked")))
+}
+
+fn run() -> io::Result<()> {
     let args: Vec<String> = env::args().collect();
+    app::register_builtins();
     if args.len() == 2 {
-        let result = num_parser::eval(&*args[1]);
-        match result {
-            Ok(res) => println!("{}", res),
-            Err(msg) => println!("Error - {}", msg),
-        }
+        println!("{}", app::eval_once(&args[1]));
         return Ok(());
     } else if args.len() > 2 {
         println!("Error - this tool accepts exactly one argument")
     }
+    // so a panic or being killed doesn't leave the terminal in raw mode, see crash.rs
+    crash::install()?;
     let mut terminal = tui::init()?;
     let app_result = App::default().run(&mut terminal);
 
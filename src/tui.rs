@@ -1,16 +1,45 @@
 use std::io::{self, stdout, Stdout};
 
-use crossterm::{execute, terminal::*};
+use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture,
+    },
+    execute,
+    terminal::*,
+};
 use ratatui::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
+// whether init() has set the terminal up, so restore() only undoes it once even if a panic and a signal both get to it
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
 pub fn init() -> io::Result<Tui> {
-    execute!(stdout(), EnterAlternateScreen)?;
+    // bracketed paste sends a paste as one event rather than a key at a time, see App::handle_paste
+    // and with the mouse captured clicks and the wheel come to us rather than the terminal, see mouse.rs
+    execute!(
+        stdout(),
+        EnterAlternateScreen,
+        EnableBracketedPaste,
+        EnableFocusChange,
+        EnableMouseCapture
+    )?;
     enable_raw_mode()?;
+    ACTIVE.store(true, Ordering::SeqCst);
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 pub fn restore() -> io::Result<()> {
-    execute!(stdout(), LeaveAlternateScreen)?;
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    execute!(
+        stdout(),
+        DisableMouseCapture,
+        DisableFocusChange,
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
     disable_raw_mode()?;
     Ok(())
 }